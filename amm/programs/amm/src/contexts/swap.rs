@@ -101,8 +101,8 @@ impl<'info> Swap<'info> {
         let cpi_program = self.token_program.to_account_info();
 
         let (from, to) = match is_x {
-            true => (self.user.to_account_info(), self.vault_x.to_account_info()),
-            false => (self.user.to_account_info(), self.vault_y.to_account_info()),
+            true => (self.user_mint_x_ata.to_account_info(), self.vault_x.to_account_info()),
+            false => (self.user_mint_y_ata.to_account_info(), self.vault_y.to_account_info()),
         };
 
         let cpi_accounts = Transfer {
@@ -121,15 +121,16 @@ impl<'info> Swap<'info> {
     pub fn withdraw_token(&mut self, is_x: bool, amount: u64) -> Result<()> {
         let cpi_program = self.token_program.to_account_info();
 
+        // Depositing X pays out Y and vice versa
         let (from, to) = match is_x {
-            true => (self.vault_x.to_account_info(), self.user.to_account_info()),
-            false => (self.vault_y.to_account_info(), self.user.to_account_info()),
+            true => (self.vault_y.to_account_info(), self.user_mint_y_ata.to_account_info()),
+            false => (self.vault_x.to_account_info(), self.user_mint_x_ata.to_account_info()),
         };
 
         let cpi_accounts = Transfer {
             from,
             to,
-            authority: self.user.to_account_info(),
+            authority: self.config.to_account_info(),
         };
 
         let seeds = &[
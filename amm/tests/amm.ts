@@ -1,16 +1,240 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Amm } from "../target/types/amm";
+import { Keypair, PublicKey, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { randomBytes } from "crypto";
+import { BN } from "bn.js";
+import { expect } from "chai";
 
 describe("amm", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.Amm as Program<Amm>;
+  const connection = anchor.getProvider().connection;
 
-  it("Is initialized!", async () => {
-    // Add your test here.
-    const tx = await program.methods.initialize().rpc();
-    console.log("Your transaction signature", tx);
+  const LIQUIDITY = 1_000_000_000;
+  const TRADER_FUNDS = 100_000_000;
+  const FEE_BPS = 30;
+
+  let lpProvider = new Keypair();
+  let trader = new Keypair();
+  let seed = new BN(randomBytes(8));
+  let config = PublicKey.findProgramAddressSync(
+    [Buffer.from("config"), seed.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+  let mintLp = PublicKey.findProgramAddressSync(
+    [Buffer.from("lp"), config.toBuffer()],
+    program.programId
+  )[0];
+  let mintX: PublicKey;
+  let mintY: PublicKey;
+  let vaultX: PublicKey;
+  let vaultY: PublicKey;
+
+  const balance = async (account: PublicKey) =>
+    Number((await getAccount(connection, account)).amount);
+
+  const ata = (mint: PublicKey, owner: PublicKey) =>
+    getAssociatedTokenAddressSync(mint, owner, true);
+
+  // Trader X, trader Y, vault X and vault Y balances
+  const snapshot = () =>
+    Promise.all(
+      [
+        ata(mintX, trader.publicKey),
+        ata(mintY, trader.publicKey),
+        vaultX,
+        vaultY,
+      ].map((account) => balance(account).catch(() => 0))
+    );
+
+  const swap = (isX: boolean, amountIn: number, minAmountOut: number) =>
+    program.methods
+      .swap(isX, new BN(amountIn), new BN(minAmountOut))
+      .accountsPartial({
+        user: trader.publicKey,
+        mintX,
+        mintY,
+        mintLp,
+        config,
+        userMintXAta: ata(mintX, trader.publicKey),
+        userMintYAta: ata(mintY, trader.publicKey),
+        vaultX,
+        vaultY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .signers([trader])
+      .rpc()
+      .then(confirmTx);
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+    } catch (e) {
+      expect(e.error?.errorCode?.code ?? e.toString()).to.contain(code);
+      return;
+    }
+    expect.fail(`expected ${code}`);
+  };
+
+  it("Airdrop and mint", async () => {
+    await Promise.all(
+      [lpProvider, trader].map(async (k) => {
+        return await connection
+          .requestAirdrop(k.publicKey, 10 * LAMPORTS_PER_SOL)
+          .then(confirmTx);
+      })
+    );
+
+    [mintX, mintY] = await Promise.all(
+      [0, 1].map(() =>
+        createMint(connection, lpProvider, lpProvider.publicKey, null, 6)
+      )
+    );
+    vaultX = ata(mintX, config);
+    vaultY = ata(mintY, config);
+
+    for (const mint of [mintX, mintY]) {
+      const lpAta = await getOrCreateAssociatedTokenAccount(
+        connection,
+        lpProvider,
+        mint,
+        lpProvider.publicKey
+      );
+      await mintTo(
+        connection,
+        lpProvider,
+        mint,
+        lpAta.address,
+        lpProvider,
+        LIQUIDITY
+      );
+    }
+    // The trader only starts out holding X
+    const traderX = await getOrCreateAssociatedTokenAccount(
+      connection,
+      trader,
+      mintX,
+      trader.publicKey
+    );
+    await mintTo(
+      connection,
+      lpProvider,
+      mintX,
+      traderX.address,
+      lpProvider,
+      TRADER_FUNDS
+    );
+  });
+
+  it("Initialize", async () => {
+    await program.methods
+      .initialize(seed, FEE_BPS, null)
+      .accountsPartial({
+        initializer: lpProvider.publicKey,
+        mintX,
+        mintY,
+        config,
+        mintLp,
+        vaultX,
+        vaultY,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lpProvider])
+      .rpc()
+      .then(confirmTx);
+  });
+
+  it("Deposit", async () => {
+    await program.methods
+      .deposit(new BN(LIQUIDITY), new BN(LIQUIDITY), new BN(LIQUIDITY))
+      .accountsPartial({
+        lpProvider: lpProvider.publicKey,
+        mintX,
+        mintY,
+        config,
+        mintLp,
+        vaultX,
+        vaultY,
+        lpProviderMintXAta: ata(mintX, lpProvider.publicKey),
+        lpProviderMintYAta: ata(mintY, lpProvider.publicKey),
+        lpProviderLpAta: ata(mintLp, lpProvider.publicKey),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lpProvider])
+      .rpc()
+      .then(confirmTx);
+
+    expect(await balance(vaultX)).to.eq(LIQUIDITY);
+    expect(await balance(vaultY)).to.eq(LIQUIDITY);
+  });
+
+  it("Swap X for Y", async () => {
+    const amountIn = 10_000_000;
+    const [traderX, , vaultXBefore, vaultYBefore] = await snapshot();
+
+    // The trader has no Y account yet; the swap creates it
+    await swap(true, amountIn, 1);
+
+    const [traderXAfter, traderY, vaultXAfter, vaultYAfter] =
+      await snapshot();
+    // X moves from the trader into vault X, Y moves out of vault Y to the trader
+    expect(traderX - traderXAfter).to.eq(amountIn);
+    expect(vaultXAfter - vaultXBefore).to.eq(amountIn);
+    expect(traderY).to.eq(vaultYBefore - vaultYAfter);
+    // At a 1:1 price the fee and price impact return less than went in
+    expect(traderY).to.be.greaterThan(0).and.lessThan(amountIn);
+    const kBefore = new BN(vaultXBefore).mul(new BN(vaultYBefore));
+    const kAfter = new BN(vaultXAfter).mul(new BN(vaultYAfter));
+    expect(kAfter.gte(kBefore)).to.be.true;
+  });
+
+  it("Swap Y for X", async () => {
+    const amountIn = 5_000_000;
+    const [traderX, traderY, vaultXBefore, vaultYBefore] = await snapshot();
+
+    await swap(false, amountIn, 1);
+
+    const [traderXAfter, traderYAfter, vaultXAfter, vaultYAfter] =
+      await snapshot();
+    expect(traderY - traderYAfter).to.eq(amountIn);
+    expect(vaultYAfter - vaultYBefore).to.eq(amountIn);
+    expect(traderXAfter - traderX).to.eq(vaultXBefore - vaultXAfter);
+    expect(traderXAfter - traderX).to.be.greaterThan(0);
+  });
+
+  it("Swap below the minimum out fails", async () => {
+    // Asking for more Y than X paid in can't be met at a 1:1 price
+    await expectError(swap(true, 1_000_000, 1_000_000), "SlippageExceeded");
+  });
+
+  it("Swap of nothing fails", async () => {
+    await expectError(swap(true, 0, 0), "InvalidAmount");
   });
 });
+
+const confirmTx = async (signature: string) => {
+  const latestBlockhash = await anchor
+    .getProvider()
+    .connection.getLatestBlockhash();
+  await anchor.getProvider().connection.confirmTransaction(
+    {
+      signature,
+      ...latestBlockhash,
+    },
+    "confirmed"
+  );
+  return signature;
+};
@@ -0,0 +1,184 @@
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+// Tags 3-5 match Solend's `LendingInstruction`; the rest are mock-only.
+const REFRESH_RESERVE: u8 = 3;
+const DEPOSIT_RESERVE_LIQUIDITY: u8 = 4;
+const REDEEM_RESERVE_COLLATERAL: u8 = 5;
+const INIT_RESERVE: u8 = 200;
+const SET_BORROW_RATE: u8 = 201;
+const SET_EXCHANGE_RATE: u8 = 202;
+const BORROW: u8 = 203;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockLendingInstruction {
+    /// Accrues interest up to the current slot.
+    ///
+    /// 0. `[writable]` Reserve
+    RefreshReserve,
+
+    /// Deposits liquidity and mints collateral at the current exchange rate.
+    /// Same accounts as Solend's `deposit_reserve_liquidity`.
+    DepositReserveLiquidity { liquidity_amount: u64 },
+
+    /// Burns collateral and returns liquidity at the current exchange rate.
+    /// Same accounts as Solend's `redeem_reserve_collateral`.
+    RedeemReserveCollateral { collateral_amount: u64 },
+
+    /// Sets up a reserve account already allocated to this program.
+    ///
+    /// 0. `[writable]` Reserve, `Reserve::LEN` bytes
+    /// 1. `[]` Lending market; any key, it only seeds the market authority
+    /// 2. `[]` Liquidity mint
+    /// 3. `[]` Liquidity supply, a token account owned by the market authority
+    /// 4. `[]` Collateral mint, minted by the market authority
+    InitReserve { borrow_rate_pct: u8 },
+
+    /// Sets a flat annual borrow rate, whatever the utilization.
+    ///
+    /// 0. `[writable]` Reserve
+    SetBorrowRate { borrow_rate_pct: u8 },
+
+    /// Jumps the exchange rate to `liquidity_per_collateral_wad` by booking the
+    /// difference as outstanding loans. The rate can't drop below what the
+    /// available liquidity already backs.
+    ///
+    /// 0. `[writable]` Reserve
+    SetExchangeRate { liquidity_per_collateral_wad: u64 },
+
+    /// Lends out liquidity so interest has something to accrue on.
+    ///
+    /// 0. `[writable]` Reserve
+    /// 1. `[writable]` Liquidity supply
+    /// 2. `[writable]` Destination liquidity token account
+    /// 3. `[]` Lending market
+    /// 4. `[]` Lending market authority
+    /// 5. `[]` Token program
+    Borrow { liquidity_amount: u64 },
+}
+
+impl MockLendingInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        Ok(match tag {
+            REFRESH_RESERVE => Self::RefreshReserve,
+            DEPOSIT_RESERVE_LIQUIDITY => Self::DepositReserveLiquidity { liquidity_amount: unpack_u64(rest)? },
+            REDEEM_RESERVE_COLLATERAL => Self::RedeemReserveCollateral { collateral_amount: unpack_u64(rest)? },
+            INIT_RESERVE => Self::InitReserve { borrow_rate_pct: unpack_u8(rest)? },
+            SET_BORROW_RATE => Self::SetBorrowRate { borrow_rate_pct: unpack_u8(rest)? },
+            SET_EXCHANGE_RATE => Self::SetExchangeRate { liquidity_per_collateral_wad: unpack_u64(rest)? },
+            BORROW => Self::Borrow { liquidity_amount: unpack_u64(rest)? },
+            _ => return Err(ProgramError::InvalidInstructionData),
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        match *self {
+            Self::RefreshReserve => vec![REFRESH_RESERVE],
+            Self::DepositReserveLiquidity { liquidity_amount } => pack_u64(DEPOSIT_RESERVE_LIQUIDITY, liquidity_amount),
+            Self::RedeemReserveCollateral { collateral_amount } => pack_u64(REDEEM_RESERVE_COLLATERAL, collateral_amount),
+            Self::InitReserve { borrow_rate_pct } => vec![INIT_RESERVE, borrow_rate_pct],
+            Self::SetBorrowRate { borrow_rate_pct } => vec![SET_BORROW_RATE, borrow_rate_pct],
+            Self::SetExchangeRate { liquidity_per_collateral_wad } => pack_u64(SET_EXCHANGE_RATE, liquidity_per_collateral_wad),
+            Self::Borrow { liquidity_amount } => pack_u64(BORROW, liquidity_amount),
+        }
+    }
+}
+
+fn unpack_u8(input: &[u8]) -> Result<u8, ProgramError> {
+    match input {
+        [value] => Ok(*value),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn unpack_u64(input: &[u8]) -> Result<u64, ProgramError> {
+    input
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| ProgramError::InvalidInstructionData)
+}
+
+fn pack_u64(tag: u8, value: u64) -> Vec<u8> {
+    let mut data = vec![tag];
+    data.extend_from_slice(&value.to_le_bytes());
+    data
+}
+
+/// The PDA that owns the liquidity supply and mints collateral, derived the way
+/// Solend derives it.
+pub fn lending_market_authority(program_id: &Pubkey, lending_market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[lending_market.as_ref()], program_id)
+}
+
+pub fn refresh_reserve(program_id: Pubkey, reserve: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(reserve, false)],
+        data: MockLendingInstruction::RefreshReserve.pack(),
+    }
+}
+
+pub fn init_reserve(
+    program_id: Pubkey,
+    borrow_rate_pct: u8,
+    reserve: Pubkey,
+    lending_market: Pubkey,
+    liquidity_mint: Pubkey,
+    liquidity_supply: Pubkey,
+    collateral_mint: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve, false),
+            AccountMeta::new_readonly(lending_market, false),
+            AccountMeta::new_readonly(liquidity_mint, false),
+            AccountMeta::new_readonly(liquidity_supply, false),
+            AccountMeta::new_readonly(collateral_mint, false),
+        ],
+        data: MockLendingInstruction::InitReserve { borrow_rate_pct }.pack(),
+    }
+}
+
+pub fn set_borrow_rate(program_id: Pubkey, borrow_rate_pct: u8, reserve: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(reserve, false)],
+        data: MockLendingInstruction::SetBorrowRate { borrow_rate_pct }.pack(),
+    }
+}
+
+pub fn set_exchange_rate(program_id: Pubkey, liquidity_per_collateral_wad: u64, reserve: Pubkey) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(reserve, false)],
+        data: MockLendingInstruction::SetExchangeRate { liquidity_per_collateral_wad }.pack(),
+    }
+}
+
+pub fn borrow(
+    program_id: Pubkey,
+    liquidity_amount: u64,
+    reserve: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    destination_liquidity: Pubkey,
+    lending_market: Pubkey,
+) -> Instruction {
+    let (lending_market_authority, _) = lending_market_authority(&program_id, &lending_market);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve, false),
+            AccountMeta::new(reserve_liquidity_supply, false),
+            AccountMeta::new(destination_liquidity, false),
+            AccountMeta::new_readonly(lending_market, false),
+            AccountMeta::new_readonly(lending_market_authority, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: MockLendingInstruction::Borrow { liquidity_amount }.pack(),
+    }
+}
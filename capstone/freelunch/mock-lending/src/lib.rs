@@ -0,0 +1,17 @@
+//! A stand-in for the Solend lending program, for testing freelunch without a
+//! live deployment.
+//!
+//! Reserves use Solend's account layout and `DepositReserveLiquidity` /
+//! `RedeemReserveCollateral` use its wire format, so freelunch talks to this
+//! program through `solend_sdk` unchanged. Interest accrues on every touch
+//! instead of requiring a `RefreshReserve` in the same slot, and the extra
+//! instructions that steer the exchange rate and borrow rate have no access
+//! control. Never deploy this outside a test validator.
+
+pub mod instruction;
+pub mod processor;
+
+solana_program::declare_id!("CMGsvED6Gjm1uWVVUxqyHvNzVfQxtQWwtwsJT6dTcKvg");
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(processor::process_instruction);
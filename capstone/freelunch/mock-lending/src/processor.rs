@@ -0,0 +1,364 @@
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+use solend_sdk::math::{Decimal, Rate, TryAdd, TryMul, TrySub};
+use solend_sdk::state::{Reserve, PROGRAM_VERSION};
+
+use crate::instruction::{lending_market_authority, MockLendingInstruction};
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    match MockLendingInstruction::unpack(input)? {
+        MockLendingInstruction::RefreshReserve => process_refresh_reserve(program_id, accounts),
+        MockLendingInstruction::DepositReserveLiquidity { liquidity_amount } => {
+            process_deposit_reserve_liquidity(program_id, liquidity_amount, accounts)
+        }
+        MockLendingInstruction::RedeemReserveCollateral { collateral_amount } => {
+            process_redeem_reserve_collateral(program_id, collateral_amount, accounts)
+        }
+        MockLendingInstruction::InitReserve { borrow_rate_pct } => {
+            process_init_reserve(program_id, borrow_rate_pct, accounts)
+        }
+        MockLendingInstruction::SetBorrowRate { borrow_rate_pct } => {
+            process_set_borrow_rate(program_id, borrow_rate_pct, accounts)
+        }
+        MockLendingInstruction::SetExchangeRate { liquidity_per_collateral_wad } => {
+            process_set_exchange_rate(program_id, liquidity_per_collateral_wad, accounts)
+        }
+        MockLendingInstruction::Borrow { liquidity_amount } => process_borrow(program_id, liquidity_amount, accounts),
+    }
+}
+
+fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let reserve_info = next_account_info(&mut accounts.iter())?;
+    let reserve = load_accrued_reserve(program_id, reserve_info)?;
+    save_reserve(reserve_info, &reserve)
+}
+
+fn process_deposit_reserve_liquidity(
+    program_id: &Pubkey,
+    liquidity_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if liquidity_amount == 0 {
+        msg!("Liquidity amount provided cannot be zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_liquidity_info = next_account_info(account_info_iter)?;
+    let destination_collateral_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let mut reserve = load_accrued_reserve(program_id, reserve_info)?;
+    check_reserve_accounts(&reserve, reserve_liquidity_supply_info, reserve_collateral_mint_info, lending_market_info)?;
+    let bump = check_lending_market_authority(program_id, lending_market_info, lending_market_authority_info)?;
+
+    let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
+    save_reserve(reserve_info, &reserve)?;
+
+    invoke(
+        &spl_token::instruction::transfer(
+            token_program_info.key,
+            source_liquidity_info.key,
+            reserve_liquidity_supply_info.key,
+            user_transfer_authority_info.key,
+            &[],
+            liquidity_amount,
+        )?,
+        &[
+            source_liquidity_info.clone(),
+            reserve_liquidity_supply_info.clone(),
+            user_transfer_authority_info.clone(),
+            token_program_info.clone(),
+        ],
+    )?;
+
+    invoke_signed(
+        &spl_token::instruction::mint_to(
+            token_program_info.key,
+            reserve_collateral_mint_info.key,
+            destination_collateral_info.key,
+            lending_market_authority_info.key,
+            &[],
+            collateral_amount,
+        )?,
+        &[
+            reserve_collateral_mint_info.clone(),
+            destination_collateral_info.clone(),
+            lending_market_authority_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[lending_market_info.key.as_ref(), &[bump]]],
+    )
+}
+
+fn process_redeem_reserve_collateral(
+    program_id: &Pubkey,
+    collateral_amount: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if collateral_amount == 0 {
+        msg!("Collateral amount provided cannot be zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let source_collateral_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_collateral_mint_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let user_transfer_authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let mut reserve = load_accrued_reserve(program_id, reserve_info)?;
+    check_reserve_accounts(&reserve, reserve_liquidity_supply_info, reserve_collateral_mint_info, lending_market_info)?;
+    let bump = check_lending_market_authority(program_id, lending_market_info, lending_market_authority_info)?;
+
+    let liquidity_amount = reserve.redeem_collateral(collateral_amount)?;
+    save_reserve(reserve_info, &reserve)?;
+
+    invoke(
+        &spl_token::instruction::burn(
+            token_program_info.key,
+            source_collateral_info.key,
+            reserve_collateral_mint_info.key,
+            user_transfer_authority_info.key,
+            &[],
+            collateral_amount,
+        )?,
+        &[
+            source_collateral_info.clone(),
+            reserve_collateral_mint_info.clone(),
+            user_transfer_authority_info.clone(),
+            token_program_info.clone(),
+        ],
+    )?;
+
+    transfer_from_supply(
+        token_program_info,
+        reserve_liquidity_supply_info,
+        destination_liquidity_info,
+        lending_market_info,
+        lending_market_authority_info,
+        bump,
+        liquidity_amount,
+    )
+}
+
+fn process_init_reserve(program_id: &Pubkey, borrow_rate_pct: u8, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let liquidity_mint_info = next_account_info(account_info_iter)?;
+    let liquidity_supply_info = next_account_info(account_info_iter)?;
+    let collateral_mint_info = next_account_info(account_info_iter)?;
+
+    if reserve_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if Reserve::unpack(&reserve_info.data.borrow()).is_ok() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (authority, _) = lending_market_authority(program_id, lending_market_info.key);
+    let supply = spl_token::state::Account::unpack(&liquidity_supply_info.data.borrow())?;
+    let collateral_mint = spl_token::state::Mint::unpack(&collateral_mint_info.data.borrow())?;
+    let liquidity_mint = spl_token::state::Mint::unpack(&liquidity_mint_info.data.borrow())?;
+    if supply.owner != authority || supply.mint != *liquidity_mint_info.key {
+        msg!("Liquidity supply must hold the liquidity mint and be owned by the market authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if collateral_mint.mint_authority.unwrap_or_default() != authority {
+        msg!("Collateral mint must be minted by the market authority");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut reserve = Reserve {
+        version: PROGRAM_VERSION,
+        lending_market: *lending_market_info.key,
+        ..Reserve::default()
+    };
+    reserve.last_update.update_slot(Clock::get()?.slot);
+    reserve.liquidity.mint_pubkey = *liquidity_mint_info.key;
+    reserve.liquidity.mint_decimals = liquidity_mint.decimals;
+    reserve.liquidity.supply_pubkey = *liquidity_supply_info.key;
+    reserve.liquidity.cumulative_borrow_rate_wads = Decimal::one();
+    reserve.collateral.mint_pubkey = *collateral_mint_info.key;
+    set_flat_borrow_rate(&mut reserve, borrow_rate_pct);
+
+    save_reserve(reserve_info, &reserve)
+}
+
+fn process_set_borrow_rate(program_id: &Pubkey, borrow_rate_pct: u8, accounts: &[AccountInfo]) -> ProgramResult {
+    let reserve_info = next_account_info(&mut accounts.iter())?;
+    // Interest up to now accrues at the old rate
+    let mut reserve = load_accrued_reserve(program_id, reserve_info)?;
+    set_flat_borrow_rate(&mut reserve, borrow_rate_pct);
+    save_reserve(reserve_info, &reserve)
+}
+
+fn process_set_exchange_rate(
+    program_id: &Pubkey,
+    liquidity_per_collateral_wad: u64,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let reserve_info = next_account_info(&mut accounts.iter())?;
+    let mut reserve = load_accrued_reserve(program_id, reserve_info)?;
+    if reserve.collateral.mint_total_supply == 0 {
+        msg!("The exchange rate is fixed until collateral has been minted");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // total_supply = available + borrowed - fees, so solve for borrowed
+    let total_supply = Decimal::from(reserve.collateral.mint_total_supply)
+        .try_mul(Rate::from_scaled_val(liquidity_per_collateral_wad))?;
+    let liquidity = &mut reserve.liquidity;
+    liquidity.borrowed_amount_wads = total_supply
+        .try_add(liquidity.accumulated_protocol_fees_wads)?
+        .try_sub(Decimal::from(liquidity.available_amount))
+        .map_err(|_| {
+            msg!("Available liquidity alone backs a higher exchange rate");
+            ProgramError::InvalidArgument
+        })?;
+
+    save_reserve(reserve_info, &reserve)
+}
+
+fn process_borrow(program_id: &Pubkey, liquidity_amount: u64, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let destination_liquidity_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let token_program_info = next_account_info(account_info_iter)?;
+
+    let mut reserve = load_accrued_reserve(program_id, reserve_info)?;
+    if reserve.liquidity.supply_pubkey != *reserve_liquidity_supply_info.key
+        || reserve.lending_market != *lending_market_info.key
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let bump = check_lending_market_authority(program_id, lending_market_info, lending_market_authority_info)?;
+
+    reserve.liquidity.borrow(Decimal::from(liquidity_amount))?;
+    save_reserve(reserve_info, &reserve)?;
+
+    transfer_from_supply(
+        token_program_info,
+        reserve_liquidity_supply_info,
+        destination_liquidity_info,
+        lending_market_info,
+        lending_market_authority_info,
+        bump,
+        liquidity_amount,
+    )
+}
+
+/// Loads the reserve and accrues interest up to the current slot, which Solend
+/// would instead require a `RefreshReserve` for.
+fn load_accrued_reserve(program_id: &Pubkey, reserve_info: &AccountInfo) -> Result<Reserve, ProgramError> {
+    if reserve_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    let slot = Clock::get()?.slot;
+    reserve.accrue_interest(slot)?;
+    reserve.last_update.update_slot(slot);
+    Ok(reserve)
+}
+
+fn save_reserve(reserve_info: &AccountInfo, reserve: &Reserve) -> ProgramResult {
+    Reserve::pack(reserve.clone(), &mut reserve_info.data.borrow_mut())
+}
+
+fn set_flat_borrow_rate(reserve: &mut Reserve, borrow_rate_pct: u8) {
+    // Equal rates at every kink make the curve flat
+    let config = &mut reserve.config;
+    config.optimal_utilization_rate = 100;
+    config.max_utilization_rate = 100;
+    config.min_borrow_rate = borrow_rate_pct;
+    config.optimal_borrow_rate = borrow_rate_pct;
+    config.max_borrow_rate = borrow_rate_pct;
+    config.super_max_borrow_rate = borrow_rate_pct as u64;
+}
+
+fn check_reserve_accounts(
+    reserve: &Reserve,
+    reserve_liquidity_supply_info: &AccountInfo,
+    reserve_collateral_mint_info: &AccountInfo,
+    lending_market_info: &AccountInfo,
+) -> ProgramResult {
+    if reserve.liquidity.supply_pubkey != *reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if reserve.collateral.mint_pubkey != *reserve_collateral_mint_info.key {
+        msg!("Reserve collateral mint does not match the reserve collateral mint provided");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if reserve.lending_market != *lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+fn check_lending_market_authority(
+    program_id: &Pubkey,
+    lending_market_info: &AccountInfo,
+    lending_market_authority_info: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    let (authority, bump) = lending_market_authority(program_id, lending_market_info.key);
+    if authority != *lending_market_authority_info.key {
+        msg!("Derived lending market authority does not match the lending market authority provided");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    Ok(bump)
+}
+
+fn transfer_from_supply<'a>(
+    token_program_info: &AccountInfo<'a>,
+    reserve_liquidity_supply_info: &AccountInfo<'a>,
+    destination_liquidity_info: &AccountInfo<'a>,
+    lending_market_info: &AccountInfo<'a>,
+    lending_market_authority_info: &AccountInfo<'a>,
+    bump: u8,
+    amount: u64,
+) -> ProgramResult {
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program_info.key,
+            reserve_liquidity_supply_info.key,
+            destination_liquidity_info.key,
+            lending_market_authority_info.key,
+            &[],
+            amount,
+        )?,
+        &[
+            reserve_liquidity_supply_info.clone(),
+            destination_liquidity_info.clone(),
+            lending_market_authority_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[lending_market_info.key.as_ref(), &[bump]]],
+    )
+}
+
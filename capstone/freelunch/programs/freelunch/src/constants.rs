@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+#[constant]
+pub const DEFAULT_KEEPER_FEE_BPS: u16 = 50; // 0.5% on top of every harvested payout
+
+#[constant]
+pub const DEFAULT_PROTOCOL_FEE_BPS: u16 = 1000; // 10% of yield credited to PoFs
+
+#[constant]
+pub const MAX_INSTALLMENTS: usize = 12;
+
+#[constant]
+pub const PURCHASE_INTENT_DOMAIN: &[u8] = b"freelunch:purchase-intent"; // prefix of every signed purchase intent
+
+#[constant]
+pub const DEFAULT_MIN_BUFFER_BPS: u64 = 500; // 5% extra collateral on top of the APY-derived amount
+
+#[constant]
+pub const DEFAULT_MAX_PURCHASE_AMOUNT: u64 = 10_000_000_000; // 10,000 USDC
+
+#[constant]
+pub const DEFAULT_MAX_BUYER_EXPOSURE: u64 = 1_000_000_000; // 1,000 USDC outstanding per buyer
+
+#[constant]
+pub const DEFAULT_APY_FLOOR_BPS: u64 = 50; // below 0.5% the collateral requirement is impractical
+
+#[constant]
+pub const DEFAULT_APY_CAP_BPS: u64 = 2000;
+
+pub const APY_EMA_ALPHA_BPS: u64 = 1000; // weight of each new sample in the smoothed APY
+
+pub const APY_REFRESH_INTERVAL: i64 = 3600; // seconds between smoothed APY updates
+
+pub const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+pub const YIELD_INDEX_SCALE: u128 = 1_000_000_000_000; // fixed point of the per-collateral yield index
+
+pub const DISCOUNT_RATE_SCALE: u128 = 1_000_000_000_000; // fixed point of the factoring pool's per-second discount accrual
+
+#[constant]
+pub const FACTORING_DEAD_SHARES: u64 = 1_000; // first LP shares locked in the pool for good
@@ -25,4 +25,103 @@ pub enum ErrorCode {
 
     #[msg("Invalid APY.")]
     InvalidAPY,
+
+    #[msg("Invalid payment status transition.")]
+    InvalidStatusTransition,
+
+    #[msg("Math overflow.")]
+    MathOverflow,
+
+    #[msg("Payment is under dispute.")]
+    PaymentDisputed,
+
+    #[msg("Invalid dispute resolution.")]
+    InvalidResolution,
+
+    #[msg("Purchase intent is not signed by the buyer.")]
+    InvalidPurchaseIntent,
+
+    #[msg("Purchase intent has expired.")]
+    PurchaseIntentExpired,
+
+    #[msg("Purchase intent nonce already used.")]
+    InvalidNonce,
+
+    #[msg("Invalid Solend reserve.")]
+    InvalidReserve,
+
+    #[msg("No yield to harvest.")]
+    NoYieldToHarvest,
+
+    #[msg("Invalid remaining accounts.")]
+    InvalidRemainingAccounts,
+
+    #[msg("Invalid settlement pool.")]
+    InvalidSettlementPool,
+
+    #[msg("Settlement swap slippage exceeded.")]
+    SlippageExceeded,
+
+    #[msg("Invalid installment schedule.")]
+    InvalidInstallmentSchedule,
+
+    #[msg("Collateral buffer is below the protocol minimum.")]
+    BufferTooLow,
+
+    #[msg("Smoothed APY was refreshed too recently.")]
+    ApyRefreshTooSoon,
+
+    #[msg("Invalid APY parameters.")]
+    InvalidApyParams,
+
+    #[msg("No earned yield to claim for this proof-of-payment.")]
+    NothingToClaim,
+
+    #[msg("Proof-of-payment still holds unclaimed earnings.")]
+    UnclaimedEarnings,
+
+    #[msg("Protocol is paused.")]
+    ProtocolPaused,
+
+    #[msg("Purchase exceeds the maximum purchase size.")]
+    PurchaseTooLarge,
+
+    #[msg("Invalid protocol parameters.")]
+    InvalidParams,
+
+    #[msg("Purchase would exceed the buyer's credit limit.")]
+    ExposureLimitExceeded,
+
+    #[msg("Invoice amount or expiry is invalid.")]
+    InvalidInvoice,
+
+    #[msg("Invoice has expired.")]
+    InvoiceExpired,
+
+    #[msg("Proof-of-payment has been factored; its yield belongs to the factoring pool.")]
+    PaymentFactored,
+
+    #[msg("Proof-of-payment has not been factored.")]
+    PaymentNotFactored,
+
+    #[msg("Factoring pool account is required for a factored proof-of-payment.")]
+    FactoringPoolRequired,
+
+    #[msg("Not enough idle liquidity in the factoring pool.")]
+    InsufficientLiquidity,
+
+    #[msg("Account still has a balance or open proof-of-payments.")]
+    AccountNotEmpty,
+
+    #[msg("Buyer's credit limit has been lowered and can't be reset by closing.")]
+    ExposureLimitLowered,
+
+    #[msg("Payment has already been partly fulfilled.")]
+    PaymentStarted,
+
+    #[msg("Token account is not the protocol's pinned USDC or collateral account.")]
+    InvalidProtocolAccount,
+
+    #[msg("The protocol's token account for the settlement mint must be created first.")]
+    SettlementAccountMissing,
 }
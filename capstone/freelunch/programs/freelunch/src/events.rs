@@ -0,0 +1,224 @@
+use anchor_lang::prelude::*;
+use crate::state::{PaymentStatus, ProofOfFuturePayment};
+
+// Every PoF event carries the PoF address, `payment_number` and the running
+// `amount_fulfilled`, so merchant payouts can be reconciled from logs alone.
+
+#[event]
+pub struct Staked {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub staked_amount: u64, // Buyer's total stake after this deposit
+}
+
+#[event]
+pub struct Unstaked {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub staked_amount: u64, // Buyer's total stake after this withdrawal
+}
+
+#[event]
+pub struct MerchantInitialized {
+    pub merchant: Pubkey,
+    pub seed: u128,
+}
+
+#[event]
+pub struct ProofOfPaymentCreated {
+    pub proof_of_payment: Pubkey,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub payment_amount: u64,
+    pub apy_bps: u64, // APY the collateral was sized with
+    pub buffer_bps: u64,
+    pub locked_collateral: u64,
+    pub installments: u8,
+}
+
+#[event]
+pub struct InvoiceCreated {
+    pub invoice: Pubkey,
+    pub merchant: Pubkey,
+    pub invoice_number: u64,
+    pub amount: u64,
+    pub memo_hash: [u8; 32],
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct InvoiceCancelled {
+    pub invoice: Pubkey,
+    pub merchant: Pubkey,
+    pub invoice_number: u64,
+    pub expired: bool, // Closed after expiry rather than withdrawn by the merchant
+}
+
+#[event]
+pub struct InvoiceAccepted {
+    pub invoice: Pubkey,
+    pub proof_of_payment: Pubkey,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub invoice_number: u64, // Also the PoF's payment_number
+}
+
+#[event]
+pub struct CollateralToppedUp {
+    pub proof_of_payment: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub locked_collateral: u64, // PoF's lock after the top-up
+}
+
+#[event]
+pub struct PaymentAtRisk {
+    pub proof_of_payment: Pubkey,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub due_at: i64,
+    pub projected_payoff_at: Option<i64>,
+    pub shortfall: Option<u64>, // Collateral to top up to pay on time, if that is still possible
+}
+
+#[event]
+pub struct FactoringLiquidityChanged {
+    pub lp: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub deposit: bool, // false for a withdrawal
+}
+
+#[event]
+pub struct PaymentFactored {
+    pub proof_of_payment: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub face_value: u64, // What the PoF still owed, now owed to the pool
+    pub discount: u64,
+    pub payout: u64, // face_value - discount, paid to the merchant
+}
+
+#[event]
+pub struct FactoredPaymentCollected {
+    pub proof_of_payment: Pubkey,
+    pub payment_number: u64,
+    pub amount: u64,
+    pub receivables: u64, // Still owed to the pool across all factored PoFs
+}
+
+#[event]
+pub struct ProofOfPaymentClosed {
+    pub proof_of_payment: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub status: PaymentStatus, // Final status at close
+}
+
+#[event]
+pub struct BuyerAccountClosed {
+    pub buyer: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaymentSource {
+    Fulfillment, // fulfill_proof_of_payment
+    Harvest,     // harvest_and_pay
+}
+
+#[event]
+pub struct PaymentApplied {
+    pub proof_of_payment: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub amount: u64, // Credited in this instruction
+    pub amount_fulfilled: u64, // Credited in total so far
+    pub source: PaymentSource,
+}
+
+#[event]
+pub struct MerchantPaid {
+    pub proof_of_payment: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub amount: u64, // Transferred to the merchant in this instruction
+    pub amount_fulfilled: u64,
+    pub earned_unclaimed: u64, // Still claimable after this payout
+}
+
+#[event]
+pub struct PaymentStatusChanged {
+    pub proof_of_payment: Pubkey,
+    pub payment_number: u64,
+    pub from: PaymentStatus,
+    pub to: PaymentStatus,
+    pub amount_fulfilled: u64,
+}
+
+#[event]
+pub struct ProofOfPaymentCompleted {
+    pub proof_of_payment: Pubkey,
+    pub buyer: Pubkey,
+    pub merchant: Pubkey,
+    pub payment_number: u64,
+    pub amount_fulfilled: u64,
+}
+
+/// Emits `PaymentStatusChanged` if the status moved away from `from`, and
+/// `ProofOfPaymentCompleted` when the PoF has just completed.
+pub fn emit_status_change(proof_of_payment: Pubkey, proof: &ProofOfFuturePayment, from: PaymentStatus) {
+    if proof.status == from {
+        return;
+    }
+
+    emit!(PaymentStatusChanged {
+        proof_of_payment,
+        payment_number: proof.payment_number,
+        from,
+        to: proof.status,
+        amount_fulfilled: proof.amount_fulfilled,
+    });
+
+    if proof.status == PaymentStatus::Completed {
+        emit!(ProofOfPaymentCompleted {
+            proof_of_payment,
+            buyer: proof.buyer,
+            merchant: proof.merchant,
+            payment_number: proof.payment_number,
+            amount_fulfilled: proof.amount_fulfilled,
+        });
+    }
+}
+
+/// Emits `PaymentApplied` for `amount` just credited to the PoF, followed by any
+/// status change it caused.
+pub fn emit_payment(
+    proof_of_payment: Pubkey,
+    proof: &ProofOfFuturePayment,
+    from: PaymentStatus,
+    amount: u64,
+    source: PaymentSource
+) {
+    emit!(PaymentApplied {
+        proof_of_payment,
+        merchant: proof.merchant,
+        payment_number: proof.payment_number,
+        amount,
+        amount_fulfilled: proof.amount_fulfilled,
+        source,
+    });
+    emit_status_change(proof_of_payment, proof, from);
+}
+
+pub fn emit_merchant_paid(proof_of_payment: Pubkey, proof: &ProofOfFuturePayment, amount: u64) {
+    emit!(MerchantPaid {
+        proof_of_payment,
+        merchant: proof.merchant,
+        payment_number: proof.payment_number,
+        amount,
+        amount_fulfilled: proof.amount_fulfilled,
+        earned_unclaimed: proof.earned_unclaimed,
+    });
+}
@@ -0,0 +1,155 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Transfer, transfer};
+use crate::state::*;
+use crate::error::ErrorCode;
+
+#[derive(Accounts)]
+pub struct AdminConfig<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> AdminConfig<'info> {
+    /// First step of an admin rotation; the current admin stays in charge until
+    /// `new_admin` accepts.
+    pub fn set_admin(&mut self, new_admin: Pubkey) -> Result<()> {
+        self.protocol_vault.pending_admin = Some(new_admin);
+        Ok(())
+    }
+
+    /// Stops stake, purchases, fulfillment, harvest and claims. Unstaking unlocked
+    /// funds keeps working so buyers can always exit.
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        self.protocol_vault.paused = paused;
+        Ok(())
+    }
+
+    pub fn update_params(
+        &mut self,
+        min_buffer_bps: u64,
+        max_purchase_amount: u64,
+        keeper_fee_bps: u16,
+        protocol_fee_bps: u16
+    ) -> Result<()> {
+        require!(
+            min_buffer_bps <= 10000
+                && max_purchase_amount > 0
+                && keeper_fee_bps as u32 + protocol_fee_bps as u32 <= 10000,
+            ErrorCode::InvalidParams
+        );
+
+        let vault = &mut self.protocol_vault;
+        vault.min_buffer_bps = min_buffer_bps;
+        vault.max_purchase_amount = max_purchase_amount;
+        vault.keeper_fee_bps = keeper_fee_bps;
+        vault.protocol_fee_bps = protocol_fee_bps;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        constraint = protocol_vault.pending_admin == Some(new_admin.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub new_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> AcceptAdmin<'info> {
+    pub fn accept_admin(&mut self) -> Result<()> {
+        let vault = &mut self.protocol_vault;
+        vault.admin = self.new_admin.key();
+        vault.pending_admin = None;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFees<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = protocol_vault.treasury_bump
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+    )]
+    pub destination: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> WithdrawProtocolFees<'info> {
+    pub fn withdraw_protocol_fees(&mut self, amount: u64) -> Result<()> {
+        require!(amount <= self.treasury.amount, ErrorCode::InsufficientFunds);
+
+        let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[self.protocol_vault.bump]];
+        let binding = [vault_seeds];
+        let cpi_accounts = Transfer {
+            from: self.treasury.to_account_info(),
+            to: self.destination.to_account_info(),
+            authority: self.protocol_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            &binding
+        );
+        transfer(cpi_ctx, amount)
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetCreditLimit<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    #[account(mut)]
+    pub buyer_account: Account<'info, BuyerAccount>,
+}
+
+impl<'info> SetCreditLimit<'info> {
+    /// Lowering the limit below what is outstanding only blocks new purchases.
+    pub fn set_credit_limit(&mut self, max_exposure: u64) -> Result<()> {
+        self.buyer_account.max_exposure = max_exposure;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::solend::{deposit_apy_bps, load_reserve};
+
+#[derive(Accounts)]
+pub struct UpdateApyParams<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> UpdateApyParams<'info> {
+    pub fn update_apy_params(
+        &mut self,
+        apy_floor_bps: u64,
+        apy_cap_bps: u64,
+        use_smoothed_apy: bool
+    ) -> Result<()> {
+        require!(
+            apy_floor_bps > 0 && apy_floor_bps <= apy_cap_bps && apy_cap_bps <= 10000,
+            ErrorCode::InvalidApyParams
+        );
+
+        let vault = &mut self.protocol_vault;
+        vault.apy_floor_bps = apy_floor_bps;
+        vault.apy_cap_bps = apy_cap_bps;
+        vault.use_smoothed_apy = use_smoothed_apy;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct RefreshApy<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
+    pub solend_reserve: AccountInfo<'info>,
+}
+
+impl<'info> RefreshApy<'info> {
+    /// Permissionless crank: samples the reserve's deposit APY into the smoothed APY.
+    pub fn refresh_apy(&mut self) -> Result<()> {
+        let reserve = load_reserve(&self.solend_reserve)?;
+        let spot_apy_bps = deposit_apy_bps(&reserve)?;
+        self.protocol_vault.update_smoothed_apy(spot_apy_bps, Clock::get()?.unix_timestamp)
+    }
+}
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::emit_status_change;
+
+#[derive(Accounts)]
+pub struct CancelProofOfPayment<'info> {
+    // Either the buyer or the protocol admin
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // Merchant co-signature, required when the buyer cancels
+    pub merchant: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        close = rent_receiver,
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", proof_of_payment.buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: Account<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    // Whoever paid for the PoF account gets the rent back
+    #[account(
+        mut,
+        address = proof_of_payment.admin @ ErrorCode::Unauthorized
+    )]
+    pub rent_receiver: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelProofOfPayment<'info> {
+    /// Cancels a PoF nothing has been paid on and releases its collateral back to the
+    /// buyer. The buyer needs the merchant's co-signature; the admin can cancel alone.
+    pub fn cancel(&mut self) -> Result<()> {
+        let proof = &mut self.proof_of_payment;
+        let authority = self.authority.key();
+
+        let is_admin = authority == self.protocol_vault.admin;
+        let is_buyer = authority == proof.buyer;
+        let merchant_consents = self
+            .merchant
+            .as_ref()
+            .is_some_and(|merchant| merchant.key() == proof.merchant);
+
+        require!(is_admin || (is_buyer && merchant_consents), ErrorCode::Unauthorized);
+        proof.require_payable()?;
+        require!(proof.amount_fulfilled == 0, ErrorCode::PaymentStarted);
+        // The pool paid for the rest of this PoF; only a dispute can write it down
+        require!(!proof.factored, ErrorCode::PaymentFactored);
+        // Closing the PoF would strand earnings the merchant hasn't claimed yet
+        require!(proof.earned_unclaimed == 0, ErrorCode::UnclaimedEarnings);
+
+        let previous_status = proof.status;
+        proof.status.transition_to(PaymentStatus::Cancelled)?;
+
+        // Release the collateral and drop the outstanding liability
+        self.buyer_account.unlock_collateral(proof.locked_collateral)?;
+        self.protocol_vault.release_collateral(proof.locked_collateral)?;
+        self.protocol_vault.settle_liability(proof.remaining_due()?)?;
+        self.buyer_account.settle_position(proof.remaining_due()?, true)?;
+        self.protocol_vault.reclaim_allotment(proof)?;
+
+        emit_status_change(proof.key(), proof, previous_status);
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer, transfer};
+use anchor_spl::token::{Token, TokenAccount};
 use crate::state::*;
 use crate::error::ErrorCode;
+use crate::settlement::pay_merchant;
+use crate::events::emit_merchant_paid;
 
 #[derive(Accounts)]
 pub struct MerchantClaim<'info> {
@@ -11,16 +13,21 @@ pub struct MerchantClaim<'info> {
     #[account(
         mut,
         constraint = proof_of_payment.merchant == merchant.key() @ ErrorCode::Unauthorized,
+        constraint = !proof_of_payment.factored @ ErrorCode::PaymentFactored,
     )]
     pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
 
-    #[account(mut)]
-    pub buyer_account: Account<'info, BuyerAccount>,
-
-    #[account(mut)]
+    #[account(
+        mut,
+        address = protocol_vault.usdc_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_usdc_account: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = merchant,
+    )]
     pub merchant_usdc_account: Account<'info, TokenAccount>,
 
     #[account(
@@ -33,7 +40,8 @@ pub struct MerchantClaim<'info> {
     #[account(
         mut,
         seeds = [b"protocol_vault"],
-        bump = protocol_vault.bump
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
     )]
     pub protocol_vault: Account<'info, ProtocolVault>,
 
@@ -42,54 +50,36 @@ pub struct MerchantClaim<'info> {
 
 
 impl<'info> MerchantClaim<'info> {
-    /// The merchant can claim up to `amount_to_claim` from the PoF.
-    /// If the PoF can be partially paid, they get partial. If it covers the entire remainder, the PoF is closed.
-    pub fn merchant_claim(&mut self, amount_to_claim: u64) -> Result<()> {
-        let proof = &mut self.proof_of_payment;
-        let buyer_account = &mut self.buyer_account;
-        let protocol_vault: &Account<'info, ProtocolVault> = &self.protocol_vault;
-
-        // Check if already completed
-        require!(proof.completed == 0, ErrorCode::PaymentAlreadyCompleted);
-
-        // Check remaining
-        let remaining_due = proof.payment_amount
-            .checked_sub(proof.amount_fulfilled)
-            .ok_or(ErrorCode::Unauthorized)?; // shouldn't happen if completed=0
-        let claim_now = std::cmp::min(amount_to_claim, remaining_due);
-
-        // Transfer from the protocol’s USDC account to the merchant’s USDC account
-        let cpi_program = self.token_program.to_account_info();
-        let cpi_accounts = Transfer {
-            from: self.protocol_usdc_account.to_account_info(),
-            to: self.merchant_usdc_account.to_account_info(),
-            authority: self.protocol_vault.to_account_info(), 
-        };
-
-        let signer_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[protocol_vault.bump]];
-
-        let binding =&[signer_seeds];
-        let cpi_ctx = CpiContext::new_with_signer(
-            cpi_program,
-            cpi_accounts,
-            binding
-        );
-        transfer(cpi_ctx, claim_now)?;
-
-        proof.amount_fulfilled = proof.amount_fulfilled
+    /// The merchant can claim up to `amount_to_claim` of the yield already credited to
+    /// the PoF by harvest or fulfillment; claiming ahead of yield fails.
+    /// Merchants with a settlement mint pass the swap accounts in `remaining_accounts`.
+    pub fn merchant_claim(
+        &mut self,
+        amount_to_claim: u64,
+        min_amount_out: u64,
+        remaining_accounts: &'info [AccountInfo<'info>]
+    ) -> Result<()> {
+        // Capped at the earned balance, so nothing can be paid twice or before it exists
+        let claim_now = self.proof_of_payment.take_earned(amount_to_claim)?;
+
+        // Pay the merchant from the protocol’s USDC account, in their settlement token if set
+        pay_merchant(
+            &self.merchant_account,
+            &self.protocol_vault,
+            &self.protocol_usdc_account,
+            &self.merchant_usdc_account,
+            &self.token_program,
+            remaining_accounts,
+            claim_now,
+            min_amount_out,
+        )?;
+
+        self.merchant_account.amount_transacted = self.merchant_account
+            .amount_transacted
             .checked_add(claim_now)
-            .ok_or(ErrorCode::Unauthorized)?;
-
-        if proof.amount_fulfilled >= proof.payment_amount {
-            proof.completed = 1;
-            buyer_account.locked_amount = buyer_account.locked_amount
-                .checked_sub(proof.locked_collateral)
-                .ok_or(ErrorCode::Unauthorized)?;
-            buyer_account.unlockable_amount = buyer_account.unlockable_amount
-                .checked_add(proof.locked_collateral)
-                .ok_or(ErrorCode::Unauthorized)?;
-        }
+            .ok_or(ErrorCode::MathOverflow)?;
 
+        emit_merchant_paid(self.proof_of_payment.key(), &self.proof_of_payment, claim_now);
         Ok(())
     }
 }
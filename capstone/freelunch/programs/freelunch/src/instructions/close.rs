@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::constants::DEFAULT_MAX_BUYER_EXPOSURE;
+use crate::events::{BuyerAccountClosed, ProofOfPaymentClosed};
+
+#[derive(Accounts)]
+pub struct CloseProofOfPayment<'info> {
+    // Anyone can clean up; the rent only ever goes back to the payer
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = rent_receiver,
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        mut,
+        address = proof_of_payment.admin @ ErrorCode::Unauthorized
+    )]
+    pub rent_receiver: SystemAccount<'info>,
+}
+
+impl<'info> CloseProofOfPayment<'info> {
+    /// Closes a completed, cancelled or defaulted PoF once nothing is left to claim.
+    pub fn close_proof_of_payment(&mut self) -> Result<()> {
+        let proof = &self.proof_of_payment;
+        require!(proof.status.is_terminal(), ErrorCode::InvalidStatusTransition);
+        // A merchant or the factoring pool may still be owed credited yield
+        require!(proof.earned_unclaimed == 0, ErrorCode::UnclaimedEarnings);
+
+        emit!(ProofOfPaymentClosed {
+            proof_of_payment: proof.key(),
+            merchant: proof.merchant,
+            payment_number: proof.payment_number,
+            status: proof.status,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CloseBuyerAccount<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"buyer", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: Account<'info, BuyerAccount>,
+}
+
+impl<'info> CloseBuyerAccount<'info> {
+    /// Returns the rent of a buyer account with nothing staked, locked or owed. A
+    /// reopened account gets a new account number, so intents signed for this one
+    /// can't be replayed against it.
+    pub fn close_buyer_account(&mut self) -> Result<()> {
+        let buyer_account = &self.buyer_account;
+        require!(
+            buyer_account.staked_amount == 0
+                && buyer_account.locked_amount == 0
+                && buyer_account.open_pofs == 0
+                && buyer_account.outstanding_amount == 0,
+            ErrorCode::AccountNotEmpty
+        );
+        // Reopening would reset a credit limit the admin lowered
+        require!(buyer_account.max_exposure >= DEFAULT_MAX_BUYER_EXPOSURE, ErrorCode::ExposureLimitLowered);
+
+        emit!(BuyerAccountClosed {
+            buyer: buyer_account.buyer,
+        });
+        Ok(())
+    }
+}
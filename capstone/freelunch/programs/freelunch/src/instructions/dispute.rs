@@ -0,0 +1,185 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::emit_status_change;
+
+/// How the arbiter settles a disputed PoF.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DisputeResolution {
+    RefundBuyer,                  // Cancel the PoF and unlock all collateral
+    ReleaseToMerchant,            // Resume payments as if nothing happened
+    Split { merchant_bps: u16 },  // Merchant keeps `merchant_bps` of what is still due
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = proof_of_payment.buyer == buyer.key() @ ErrorCode::Unauthorized,
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+}
+
+impl<'info> OpenDispute<'info> {
+    /// Freezes fulfillment and claims until the arbiter resolves the dispute.
+    pub fn open_dispute(&mut self) -> Result<()> {
+        let proof = &mut self.proof_of_payment;
+        proof.require_payable()?;
+
+        let previous_status = proof.status;
+        proof.status.transition_to(PaymentStatus::Disputed)?;
+
+        emit_status_change(proof.key(), proof, previous_status);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(
+        constraint = arbiter.key() == protocol_vault.arbiter @ ErrorCode::Unauthorized,
+    )]
+    pub arbiter: Signer<'info>,
+
+    #[account(mut)]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", proof_of_payment.buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: Account<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    // Required when the PoF was factored, so forgiven amounts come off its receivables
+    #[account(
+        mut,
+        seeds = [b"factoring_pool"],
+        bump = factoring_pool.bump
+    )]
+    pub factoring_pool: Option<Account<'info, FactoringPool>>,
+}
+
+impl<'info> ResolveDispute<'info> {
+    pub fn resolve_dispute(&mut self, resolution: DisputeResolution) -> Result<()> {
+        let proof = &mut self.proof_of_payment;
+        require!(proof.status == PaymentStatus::Disputed, ErrorCode::InvalidStatusTransition);
+        // The split below shrinks the lock, so settle what it earned first
+        proof.accrue_yield(self.protocol_vault.yield_index)?;
+
+        let remaining_due = proof.remaining_due()?;
+        let due_at = proof.due_at();
+        let previous_status = proof.status;
+        let mut forgiven = 0;
+
+        match resolution {
+            DisputeResolution::RefundBuyer => {
+                proof.status.transition_to(PaymentStatus::Cancelled)?;
+                self.buyer_account.unlock_collateral(proof.locked_collateral)?;
+                self.protocol_vault.release_collateral(proof.locked_collateral)?;
+                self.protocol_vault.settle_liability(remaining_due)?;
+                self.buyer_account.settle_position(remaining_due, true)?;
+                forgiven = remaining_due;
+            }
+            DisputeResolution::ReleaseToMerchant => {
+                let next = proof.progress_status();
+                proof.status.transition_to(next)?;
+                self.buyer_account.settle_position(0, proof.status.is_terminal())?;
+            }
+            DisputeResolution::Split { merchant_bps } => {
+                require!(merchant_bps <= 10000, ErrorCode::InvalidResolution);
+
+                // Shrink what is still owed and release the buyer's share of collateral
+                let merchant_share = (remaining_due as u128)
+                    .checked_mul(merchant_bps as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_div(10000)
+                    .ok_or(ErrorCode::MathOverflow)? as u64;
+                let buyer_share = remaining_due
+                    .checked_sub(merchant_share)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                let released = (proof.locked_collateral as u128)
+                    .checked_mul(10000 - merchant_bps as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    .checked_div(10000)
+                    .ok_or(ErrorCode::MathOverflow)? as u64;
+
+                proof.forgive(buyer_share)?;
+                proof.locked_collateral = proof.locked_collateral
+                    .checked_sub(released)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                self.buyer_account.unlock_collateral(released)?;
+                self.protocol_vault.release_collateral(released)?;
+                self.protocol_vault.settle_liability(buyer_share)?;
+
+                // A zero share for the merchant settles the PoF immediately
+                let next = proof.progress_status();
+                proof.status.transition_to(next)?;
+                self.buyer_account.settle_position(buyer_share, proof.status.is_terminal())?;
+                let released = proof.clear_installments()?;
+                self.buyer_account.unlock_collateral(released)?;
+                self.protocol_vault.release_collateral(released)?;
+                forgiven = buyer_share;
+            }
+        }
+
+        // The pool already paid the merchant for a factored PoF, so whatever the buyer
+        // is let off comes out of the receivables: the LPs take the loss, not the merchant.
+        // The discount on the forgiven part stops accruing with it.
+        if proof.factored && forgiven > 0 {
+            let pool = self.factoring_pool
+                .as_mut()
+                .ok_or(ErrorCode::FactoringPoolRequired)?;
+            let now = Clock::get()?.unix_timestamp;
+            let dropped = if proof.status.is_terminal() {
+                proof.factoring_discount
+            } else {
+                ((proof.factoring_discount as u128)
+                    .checked_mul(forgiven as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+                    / remaining_due as u128) as u64
+            };
+            pool.accrue_discount(now)?;
+            pool.settle_receivable(forgiven)?;
+            pool.drop_discount(dropped, proof.factored_at, due_at, now)?;
+            proof.factoring_discount -= dropped;
+        }
+        if proof.status.is_terminal() {
+            self.protocol_vault.reclaim_allotment(proof)?;
+        }
+
+        emit_status_change(proof.key(), proof, previous_status);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetArbiter<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> SetArbiter<'info> {
+    pub fn set_arbiter(&mut self, arbiter: Pubkey) -> Result<()> {
+        self.protocol_vault.arbiter = arbiter;
+        Ok(())
+    }
+}
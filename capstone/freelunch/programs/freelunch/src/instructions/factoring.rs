@@ -0,0 +1,394 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount, Transfer, transfer};
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::{FactoredPaymentCollected, FactoringLiquidityChanged, PaymentFactored};
+
+#[derive(Accounts)]
+pub struct InitFactoringPool<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    #[account(address = protocol_vault.usdc_mint)]
+    pub usdc_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = FactoringPool::INIT_SPACE + 8,
+        seeds = [b"factoring_pool"],
+        bump
+    )]
+    pub factoring_pool: Account<'info, FactoringPool>,
+
+    // LP deposits and collected yield sit here; the vault signs payouts like it does for the treasury
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"factoring_liquidity"],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = protocol_vault,
+    )]
+    pub factoring_liquidity: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitFactoringPool<'info> {
+    pub fn init_factoring_pool(&mut self, discount_bps: u64, bumps: &InitFactoringPoolBumps) -> Result<()> {
+        require!(discount_bps <= 10000, ErrorCode::InvalidParams);
+
+        self.factoring_pool.set_inner(FactoringPool {
+            total_shares: 0,
+            receivables: 0,
+            discount_bps,
+            total_discount: 0,
+            unearned_discount: 0,
+            discount_rate: 0,
+            accrued_at: 0,
+            bump: bumps.factoring_pool,
+            liquidity_bump: bumps.factoring_liquidity,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetFactoringDiscount<'info> {
+    #[account(
+        constraint = admin.key() == protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_pool"],
+        bump = factoring_pool.bump
+    )]
+    pub factoring_pool: Account<'info, FactoringPool>,
+}
+
+impl<'info> SetFactoringDiscount<'info> {
+    /// Applies to PoFs factored from now on; already-factored PoFs keep their price.
+    pub fn set_factoring_discount(&mut self, discount_bps: u64) -> Result<()> {
+        require!(discount_bps <= 10000, ErrorCode::InvalidParams);
+        self.factoring_pool.discount_bps = discount_bps;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FactoringLiquidity<'info> {
+    #[account(mut)]
+    pub lp: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = lp,
+    )]
+    pub lp_usdc_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = lp,
+        space = LiquidityPosition::INIT_SPACE + 8,
+        seeds = [b"liquidity_position", lp.key().as_ref()],
+        bump
+    )]
+    pub liquidity_position: Account<'info, LiquidityPosition>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_pool"],
+        bump = factoring_pool.bump
+    )]
+    pub factoring_pool: Account<'info, FactoringPool>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_liquidity"],
+        bump = factoring_pool.liquidity_bump
+    )]
+    pub factoring_liquidity: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FactoringLiquidity<'info> {
+    pub fn deposit_factoring_liquidity(&mut self, amount: u64) -> Result<()> {
+        require!(!self.protocol_vault.paused, ErrorCode::ProtocolPaused);
+        require!(amount > 0, ErrorCode::InvalidPurchaseAmount);
+
+        self.factoring_pool.accrue_discount(Clock::get()?.unix_timestamp)?;
+        let shares = self.factoring_pool.shares_for_deposit(amount, self.factoring_liquidity.amount)?;
+        require!(shares > 0, ErrorCode::InvalidPurchaseAmount);
+
+        let cpi_accounts = Transfer {
+            from: self.lp_usdc_account.to_account_info(),
+            to: self.factoring_liquidity.to_account_info(),
+            authority: self.lp.to_account_info(),
+        };
+        transfer(CpiContext::new(self.token_program.to_account_info(), cpi_accounts), amount)?;
+
+        self.liquidity_position.lp = self.lp.key();
+        self.liquidity_position.shares = self.liquidity_position.shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.factoring_pool.issue_shares(shares)?;
+
+        emit!(FactoringLiquidityChanged {
+            lp: self.lp.key(),
+            amount,
+            shares,
+            deposit: true,
+        });
+        Ok(())
+    }
+
+    /// Redeems `shares` at the pool's current value. Only idle liquidity can leave;
+    /// the rest comes back as factored PoFs are collected.
+    pub fn withdraw_factoring_liquidity(&mut self, shares: u64) -> Result<()> {
+        require!(
+            shares > 0 && shares <= self.liquidity_position.shares,
+            ErrorCode::InsufficientFunds
+        );
+
+        self.factoring_pool.accrue_discount(Clock::get()?.unix_timestamp)?;
+        let amount = self.factoring_pool.amount_for_shares(shares, self.factoring_liquidity.amount)?;
+        require!(amount <= self.factoring_liquidity.amount, ErrorCode::InsufficientLiquidity);
+
+        self.liquidity_position.shares -= shares;
+        self.factoring_pool.total_shares = self.factoring_pool.total_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_from_vault(
+            &self.protocol_vault,
+            &self.factoring_liquidity,
+            self.lp_usdc_account.to_account_info(),
+            &self.token_program,
+            amount,
+        )?;
+
+        emit!(FactoringLiquidityChanged {
+            lp: self.lp.key(),
+            amount,
+            shares,
+            deposit: false,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FactorPayment<'info> {
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_account: Account<'info, MerchantAccount>,
+
+    #[account(
+        mut,
+        constraint = proof_of_payment.merchant == merchant.key() @ ErrorCode::Unauthorized,
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    // Paid in USDC regardless of the merchant's settlement preference
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = merchant,
+    )]
+    pub merchant_usdc_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_pool"],
+        bump = factoring_pool.bump
+    )]
+    pub factoring_pool: Account<'info, FactoringPool>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_liquidity"],
+        bump = factoring_pool.liquidity_bump
+    )]
+    pub factoring_liquidity: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> FactorPayment<'info> {
+    /// Sells what is still due on the PoF to the factoring pool. The merchant gets it
+    /// now, less the pool's discount for the time left until `due_at`, and every later
+    /// yield credit on the PoF goes to the pool instead.
+    pub fn merchant_factor_payment(&mut self, min_payout: u64) -> Result<()> {
+        let proof = &mut self.proof_of_payment;
+        proof.require_payable()?;
+        require!(!proof.factored, ErrorCode::PaymentFactored);
+        // Yield already credited is the merchant's; claim it before selling the rest
+        require!(proof.earned_unclaimed == 0, ErrorCode::UnclaimedEarnings);
+
+        let now = Clock::get()?.unix_timestamp;
+        let face_value = proof.remaining_due()?;
+        let seconds_early = proof.due_at().saturating_sub(now);
+        let discount = self.factoring_pool.discount(face_value, seconds_early)?;
+        let payout = face_value - discount;
+        require!(payout > 0, ErrorCode::InvalidPurchaseAmount);
+        require!(payout >= min_payout, ErrorCode::SlippageExceeded);
+        require!(payout <= self.factoring_liquidity.amount, ErrorCode::InsufficientLiquidity);
+
+        proof.factored = true;
+        proof.factored_at = now;
+        proof.factoring_discount = discount;
+        self.factoring_pool.accrue_discount(now)?;
+        self.factoring_pool.add_receivable(face_value, discount, now, proof.due_at())?;
+        self.merchant_account.amount_transacted = self.merchant_account
+            .amount_transacted
+            .checked_add(payout)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        transfer_from_vault(
+            &self.protocol_vault,
+            &self.factoring_liquidity,
+            self.merchant_usdc_account.to_account_info(),
+            &self.token_program,
+            payout,
+        )?;
+
+        emit!(PaymentFactored {
+            proof_of_payment: self.proof_of_payment.key(),
+            merchant: self.proof_of_payment.merchant,
+            payment_number: self.proof_of_payment.payment_number,
+            face_value,
+            discount,
+            payout,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CollectFactoredPayment<'info> {
+    #[account(
+        mut,
+        constraint = proof_of_payment.factored @ ErrorCode::PaymentNotFactored,
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_pool"],
+        bump = factoring_pool.bump
+    )]
+    pub factoring_pool: Account<'info, FactoringPool>,
+
+    #[account(
+        mut,
+        seeds = [b"factoring_liquidity"],
+        bump = factoring_pool.liquidity_bump
+    )]
+    pub factoring_liquidity: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        address = protocol_vault.usdc_account @ ErrorCode::InvalidProtocolAccount,
+    )]
+    pub protocol_usdc_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CollectFactoredPayment<'info> {
+    /// Permissionless crank: moves yield harvested for a factored PoF into the pool.
+    /// Once the PoF is settled its discount is earned in full.
+    pub fn collect_factored_payment(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proof = &mut self.proof_of_payment;
+        let amount = proof.take_earned(u64::MAX)?;
+        self.factoring_pool.accrue_discount(now)?;
+        self.factoring_pool.settle_receivable(amount)?;
+        if proof.status.is_terminal() {
+            let discount = std::mem::take(&mut proof.factoring_discount);
+            self.factoring_pool.drop_discount(discount, proof.factored_at, proof.due_at(), now)?;
+        }
+
+        transfer_from_vault(
+            &self.protocol_vault,
+            &self.protocol_usdc_account,
+            self.factoring_liquidity.to_account_info(),
+            &self.token_program,
+            amount,
+        )?;
+
+        emit!(FactoredPaymentCollected {
+            proof_of_payment: self.proof_of_payment.key(),
+            payment_number: self.proof_of_payment.payment_number,
+            amount,
+            receivables: self.factoring_pool.receivables,
+        });
+        Ok(())
+    }
+}
+
+/// Moves `amount` out of a token account the protocol vault PDA owns.
+fn transfer_from_vault<'info>(
+    protocol_vault: &Account<'info, ProtocolVault>,
+    from: &Account<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64,
+) -> Result<()> {
+    let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[protocol_vault.bump]];
+    let binding = [vault_seeds];
+    let cpi_accounts = Transfer {
+        from: from.to_account_info(),
+        to,
+        authority: protocol_vault.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &binding);
+    transfer(cpi_ctx, amount)
+}
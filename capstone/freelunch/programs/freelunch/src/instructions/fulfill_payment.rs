@@ -4,36 +4,56 @@ use solend_sdk::instruction::redeem_reserve_collateral;
 use solend_sdk::solana_program::program::invoke_signed;
 use crate::state::*;
 use crate::error::ErrorCode;
+use crate::solend::{collateral_for_liquidity, redeemable_liquidity, load_reserve};
+use crate::settlement::pay_merchant;
+use crate::events::{emit_merchant_paid, emit_payment, PaymentSource};
 
 // We'll assume the protocol or an admin calls this on a schedule (like daily or weekly).
 #[derive(Accounts)]
 pub struct FulfillProofOfPayment<'info> {
-    /// CHECK: This is the protocol's trusted signer; signature verified in instruction.
-    #[account(signer)]
-    pub protocol_signer: AccountInfo<'info>,
+    // The protocol's trusted signer
+    #[account(
+        address = protocol_vault.admin @ ErrorCode::Unauthorized,
+    )]
+    pub protocol_signer: Signer<'info>,
 
     // The protocol vault (PDA)
     #[account(
         mut,
         seeds = [b"protocol_vault"],
-        bump = protocol_vault.bump
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
     )]
     pub protocol_vault: Account<'info, ProtocolVault>,
 
     // The protocol's token account that holds USDC (harvested from Solend).
-    #[account(mut)]
+    #[account(
+        mut,
+        address = protocol_vault.usdc_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_usdc_account: Account<'info, TokenAccount>,
 
     // The merchant's USDC token account (the final destination of the funds).
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = proof_of_payment.merchant,
+    )]
     pub merchant_usdc_account: Account<'info, TokenAccount>,
 
-    // The ProofOfFuturePayment record to fulfill
-    #[account(mut)]
+    // The ProofOfFuturePayment record to fulfill; factored PoFs are paid through harvest
+    #[account(
+        mut,
+        constraint = !proof_of_payment.factored @ ErrorCode::PaymentFactored,
+    )]
     pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
 
     // The buyer's account, so we can unlock collateral if we fully pay the PoF
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [b"buyer", proof_of_payment.buyer.key().as_ref()],
+        bump
+    )]
     pub buyer_account: Account<'info, BuyerAccount>,
 
     #[account(
@@ -43,44 +63,90 @@ pub struct FulfillProofOfPayment<'info> {
     )]
     pub merchant_account: Account<'info, MerchantAccount>,
 
-    // Solends accounts
-    #[account(mut)]
+    // Solend accounts, pinned to the reserve recorded in protocol_vault
     /// CHECK: This is solend program
+    #[account(address = protocol_vault.solend_program @ ErrorCode::InvalidReserve)]
     pub solend_program: AccountInfo<'info>,
-    /// CHECK: This is solend program
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(mut, address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
     pub solend_reserve: AccountInfo<'info>,
-    /// CHECK: This is the Solend liquidity supply. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend liquidity supply of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_liquidity_supply @ ErrorCode::InvalidReserve)]
     pub reserve_liquidity_supply: AccountInfo<'info>,
-    /// CHECK: This is the Solend collateral mint for cUSDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend collateral mint for cUSDC of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_collateral_mint @ ErrorCode::InvalidReserve)]
     pub reserve_collateral_mint: AccountInfo<'info>,
-    /// CHECK: This is the Solend lending market. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend lending market of the pinned reserve
+    #[account(address = protocol_vault.lending_market @ ErrorCode::InvalidReserve)]
     pub lending_market: AccountInfo<'info>,
-    /// CHECK: This is the Solend lending market authority. Verified via Solend CPI instructions.
+    /// CHECK: This is the Lending Market Authority. Solend derives it from lending_market during the CPI.
     pub lending_market_authority: AccountInfo<'info>,
 
+    #[account(
+        mut,
+        address = protocol_vault.collateral_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_collateral_account: Account<'info, TokenAccount>,
 
+    // Receives the protocol fee
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = protocol_vault.treasury_bump
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+
     pub token_program: Program<'info, Token>,
 }
 
 impl<'info> FulfillProofOfPayment<'info> {
     pub fn complete_payment(
         &mut self,
-        amount_to_pay_now: u64
+        amount_to_pay_now: u64,
+        min_amount_out: u64,
+        remaining_accounts: &'info [AccountInfo<'info>]
     ) -> Result<()> {
         let vault_bump = self.protocol_vault.bump;
 
+        // Like harvest, only yield accrued above staked principal can be paid out,
+        // and of that only what this PoF has been allotted
+        let reserve = load_reserve(&self.solend_reserve)?;
+        let collateral_value = redeemable_liquidity(&reserve, self.protocol_collateral_account.amount)?;
+        let budget = self.protocol_vault.payable_yield(collateral_value, self.protocol_vault.protocol_fee_bps as u64)?;
+        self.protocol_vault.allot_yield(budget)?;
+        self.proof_of_payment.require_payable()?;
+        self.proof_of_payment.accrue_yield(self.protocol_vault.yield_index)?;
+        let usable = self.proof_of_payment.take_allotted(amount_to_pay_now.min(budget));
+        require!(usable > 0, ErrorCode::NoYieldToHarvest);
+        self.protocol_vault.unallot_yield(usable)?;
+
+        // Credit the earned yield first so we only move what is still due
+        let previous_status = self.proof_of_payment.status;
+        let pay_now = self.proof_of_payment.credit_earned(usable)?;
+        self.protocol_vault.settle_liability(pay_now)?;
+
+        // The treasury's cut is skimmed from the yield on top of the payment
+        let protocol_fee = self.protocol_vault.protocol_fee(pay_now)?;
+        let redeemed = pay_now
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.protocol_vault.record_yield(redeemed)?;
+        self.protocol_vault.record_protocol_fee(protocol_fee)?;
+
+        // Redeem enough cUSDC to cover the USDC we pay out
+        let collateral_to_redeem = collateral_for_liquidity(&reserve, redeemed)?;
+
         // 1) Build a redeem_reserve_collateral CPI instruction
         let redeem_ix = redeem_reserve_collateral(
             self.solend_program.key(),
-            amount_to_pay_now, // cUSDC
+            collateral_to_redeem, // cUSDC
             self.protocol_collateral_account.key(),
             self.protocol_usdc_account.key(),
             self.solend_reserve.key(),
-            self.reserve_liquidity_supply.key(),
             self.reserve_collateral_mint.key(),
+            self.reserve_liquidity_supply.key(),
             self.lending_market.key(),
-            self.lending_market_authority.key(),
+            self.protocol_vault.key(),
         );
 
         // 2) Gather the accounts required by the redeem instruction
@@ -92,6 +158,7 @@ impl<'info> FulfillProofOfPayment<'info> {
             self.reserve_collateral_mint.to_account_info(),
             self.lending_market.to_account_info(),
             self.lending_market_authority.to_account_info(),
+            self.protocol_vault.to_account_info(),
             self.token_program.to_account_info(),
             self.solend_program.to_account_info(),
         ];
@@ -103,55 +170,58 @@ impl<'info> FulfillProofOfPayment<'info> {
             &[&[b"protocol_vault", &[vault_bump]]],
         )?;
 
-    // 2) Transfer from protocol_usdc_account to merchant_usdc_account
-        //    using the vault's authority (PDA).
-        let cpi_progmram = self.token_program.to_account_info();
-        let cpi_accounts = Transfer {
-            from: self.protocol_usdc_account.to_account_info(),
-            to: self.merchant_usdc_account.to_account_info(),
-            authority: self.protocol_vault.to_account_info(),
-        };
-
-        let binding = [vault_bump];
-        let vault_seeds = &[&[b"protocol_vault".as_ref(), &binding][..]];
-
-        let cpi_ctx = CpiContext::new_with_signer(
-            cpi_progmram,
-            cpi_accounts,
-            vault_seeds,
-            
-        );
-
-        transfer(cpi_ctx.with_signer(vault_seeds), amount_to_pay_now)?;
-
-        // 3) Update PoF, buyer, merchant as usual
-        let proof = &mut self.proof_of_payment;
-        require!(proof.completed == 0, ErrorCode::PaymentAlreadyCompleted);
-
-        let remain = proof.payment_amount
-            .checked_sub(proof.amount_fulfilled)
-            .ok_or(ErrorCode::Unauthorized)?;
-        let pay_now = std::cmp::min(amount_to_pay_now, remain);
+        if protocol_fee > 0 {
+            let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[vault_bump]];
+            let binding = [vault_seeds];
+            let cpi_accounts = Transfer {
+                from: self.protocol_usdc_account.to_account_info(),
+                to: self.treasury.to_account_info(),
+                authority: self.protocol_vault.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                cpi_accounts,
+                &binding
+            );
+            transfer(cpi_ctx, protocol_fee)?;
+        }
 
-        proof.amount_fulfilled = proof.amount_fulfilled
-            .checked_add(pay_now)
-            .ok_or(ErrorCode::Unauthorized)?;
+        // 2) Pay the merchant from protocol_usdc_account using the vault's authority (PDA),
+        //    swapping into their settlement token if they set one.
+        let pay_now = self.proof_of_payment.take_earned(pay_now)?;
+        pay_merchant(
+            &self.merchant_account,
+            &self.protocol_vault,
+            &self.protocol_usdc_account,
+            &self.merchant_usdc_account,
+            &self.token_program,
+            remaining_accounts,
+            pay_now,
+            min_amount_out,
+        )?;
 
-        // If fully paid, free the buyer's locked collateral
-        if proof.amount_fulfilled >= proof.payment_amount {
-            proof.completed = 1;
-            self.buyer_account.locked_amount = self.buyer_account.locked_amount
-                .checked_sub(proof.locked_collateral)
-                .ok_or(ErrorCode::Unauthorized)?;
-            self.buyer_account.unlockable_amount = self.buyer_account.unlockable_amount
-                .checked_add(proof.locked_collateral)
-                .ok_or(ErrorCode::Unauthorized)?;
+        // 3) Free collateral for cleared installments (all of it once fully paid)
+        let released = self.proof_of_payment.clear_installments()?;
+        self.buyer_account.unlock_collateral(released)?;
+        self.protocol_vault.release_collateral(released)?;
+        self.buyer_account.settle_position(pay_now, self.proof_of_payment.status.is_terminal())?;
+        if self.proof_of_payment.status.is_terminal() {
+            self.protocol_vault.reclaim_allotment(&mut self.proof_of_payment)?;
         }
 
         self.merchant_account.amount_transacted = self.merchant_account
             .amount_transacted
             .checked_add(pay_now)
             .ok_or(ErrorCode::Unauthorized)?;
+
+        emit_payment(
+            self.proof_of_payment.key(),
+            &self.proof_of_payment,
+            previous_status,
+            pay_now,
+            PaymentSource::Fulfillment
+        );
+        emit_merchant_paid(self.proof_of_payment.key(), &self.proof_of_payment, pay_now);
         Ok(())
         }
 }
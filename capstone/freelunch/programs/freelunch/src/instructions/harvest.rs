@@ -0,0 +1,222 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Transfer, transfer};
+use solend_sdk::instruction::redeem_reserve_collateral;
+use solend_sdk::solana_program::program::invoke_signed;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::{emit_payment, PaymentSource};
+use crate::solend::{collateral_for_liquidity, redeemable_liquidity, load_reserve};
+
+// Each PoF is passed through `remaining_accounts` as a group of:
+// [proof_of_payment, buyer_account]
+pub const HARVEST_ACCOUNTS_PER_POF: usize = 2;
+
+#[derive(Accounts)]
+pub struct HarvestAndPay<'info> {
+    // Anyone can crank; they earn the keeper fee
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+    )]
+    pub keeper_usdc_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    // The protocol's token account that receives redeemed USDC
+    #[account(
+        mut,
+        address = protocol_vault.usdc_account @ ErrorCode::InvalidProtocolAccount,
+    )]
+    pub protocol_usdc_account: Account<'info, TokenAccount>,
+
+    // cUSDC held on behalf of all stakers
+    #[account(
+        mut,
+        address = protocol_vault.collateral_account @ ErrorCode::InvalidProtocolAccount,
+    )]
+    pub protocol_collateral_account: Account<'info, TokenAccount>,
+
+    // Receives the protocol fee
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = protocol_vault.treasury_bump
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+
+    // Solend accounts, pinned to the reserve recorded in protocol_vault
+    /// CHECK: This is solend program
+    #[account(address = protocol_vault.solend_program @ ErrorCode::InvalidReserve)]
+    pub solend_program: AccountInfo<'info>,
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(mut, address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
+    pub solend_reserve: AccountInfo<'info>,
+    /// CHECK: This is the Solend liquidity supply of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_liquidity_supply @ ErrorCode::InvalidReserve)]
+    pub reserve_liquidity_supply: AccountInfo<'info>,
+    /// CHECK: This is the Solend collateral mint for cUSDC of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_collateral_mint @ ErrorCode::InvalidReserve)]
+    pub reserve_collateral_mint: AccountInfo<'info>,
+    /// CHECK: This is the Solend lending market of the pinned reserve
+    #[account(address = protocol_vault.lending_market @ ErrorCode::InvalidReserve)]
+    pub lending_market: AccountInfo<'info>,
+    /// CHECK: This is the Lending Market Authority. Solend derives it from lending_market during the CPI.
+    pub lending_market_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> HarvestAndPay<'info> {
+    /// Redeems only the yield accrued above staked principal and credits the given
+    /// PoFs what they have been allotted of it, for their merchants to claim. Yield is
+    /// allotted to every open PoF pro rata to its locked collateral, so which PoFs a
+    /// keeper passes decides only who is paid now, not who earned what. The keeper
+    /// earns `keeper_fee_bps` and the treasury `protocol_fee_bps` on top of what is credited.
+    pub fn harvest_and_pay(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(
+            !remaining_accounts.is_empty() && remaining_accounts.len() % HARVEST_ACCOUNTS_PER_POF == 0,
+            ErrorCode::InvalidRemainingAccounts
+        );
+
+        // 1) Yield = value of our cUSDC minus the principal stakers put in
+        let reserve = load_reserve(&self.solend_reserve)?;
+        let collateral_value = redeemable_liquidity(&reserve, self.protocol_collateral_account.amount)?;
+        // Leave room for the keeper and protocol fees so credits + fees never exceed the yield
+        let fee_bps = self.protocol_vault.keeper_fee_bps as u64 + self.protocol_vault.protocol_fee_bps as u64;
+        let mut budget = self.protocol_vault.payable_yield(collateral_value, fee_bps)?;
+        require!(budget > 0, ErrorCode::NoYieldToHarvest);
+
+        // 2) Share out whatever accrued since the last harvest across all open PoFs
+        self.protocol_vault.allot_yield(budget)?;
+
+        // 3) Credit each given PoF its allotment while the budget lasts
+        let mut seen: Vec<&Pubkey> = Vec::with_capacity(remaining_accounts.len() / HARVEST_ACCOUNTS_PER_POF);
+        let mut total_credited: u64 = 0;
+        for group in remaining_accounts.chunks(HARVEST_ACCOUNTS_PER_POF) {
+            // The same PoF twice would be paid from a stale copy
+            require!(!seen.contains(&group[0].key), ErrorCode::InvalidRemainingAccounts);
+            seen.push(group[0].key);
+            if budget == 0 {
+                break;
+            }
+
+            let mut proof = Account::<ProofOfFuturePayment>::try_from(&group[0])?;
+            if !proof.status.is_payable() {
+                continue;
+            }
+            proof.accrue_yield(self.protocol_vault.yield_index)?;
+            let usable = proof.take_allotted(budget);
+            if usable == 0 {
+                continue;
+            }
+
+            // Buyer accounts only exist at their PDAs, so matching keys is enough
+            let mut buyer_account = Account::<BuyerAccount>::try_from(&group[1])?;
+            require_keys_eq!(buyer_account.buyer, proof.buyer, ErrorCode::InvalidRemainingAccounts);
+
+            // Anything past what is still due goes back to be allotted again
+            self.protocol_vault.unallot_yield(usable)?;
+            let previous_status = proof.status;
+            let credited = proof.credit_earned(usable)?;
+            budget -= credited;
+            self.protocol_vault.settle_liability(credited)?;
+
+            let released = proof.clear_installments()?;
+            buyer_account.unlock_collateral(released)?;
+            self.protocol_vault.release_collateral(released)?;
+            buyer_account.settle_position(credited, proof.status.is_terminal())?;
+            if proof.status.is_terminal() {
+                self.protocol_vault.reclaim_allotment(&mut proof)?;
+            }
+
+            emit_payment(group[0].key(), &proof, previous_status, credited, PaymentSource::Harvest);
+
+            // Persist now so a buyer shared by several PoFs is reloaded fresh
+            proof.exit(&crate::ID)?;
+            buyer_account.exit(&crate::ID)?;
+
+            total_credited = total_credited
+                .checked_add(credited)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        require!(total_credited > 0, ErrorCode::NoYieldToHarvest);
+
+        let keeper_fee = self.protocol_vault.keeper_fee(total_credited)?;
+        let protocol_fee = self.protocol_vault.protocol_fee(total_credited)?;
+        let harvested = total_credited
+            .checked_add(keeper_fee)
+            .and_then(|total| total.checked_add(protocol_fee))
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.protocol_vault.record_yield(harvested)?;
+        self.protocol_vault.record_protocol_fee(protocol_fee)?;
+
+        // 4) Redeem just the harvested yield from Solend
+        let vault_bump = self.protocol_vault.bump;
+        let redeem_ix = redeem_reserve_collateral(
+            self.solend_program.key(),
+            collateral_for_liquidity(&reserve, harvested)?,
+            self.protocol_collateral_account.key(),
+            self.protocol_usdc_account.key(),
+            self.solend_reserve.key(),
+            self.reserve_collateral_mint.key(),
+            self.reserve_liquidity_supply.key(),
+            self.lending_market.key(),
+            self.protocol_vault.key(),
+        );
+
+        let redeem_infos = &[
+            self.protocol_collateral_account.to_account_info(),
+            self.protocol_usdc_account.to_account_info(),
+            self.solend_reserve.to_account_info(),
+            self.reserve_liquidity_supply.to_account_info(),
+            self.reserve_collateral_mint.to_account_info(),
+            self.lending_market.to_account_info(),
+            self.lending_market_authority.to_account_info(),
+            self.protocol_vault.to_account_info(),
+            self.token_program.to_account_info(),
+            self.solend_program.to_account_info(),
+        ];
+
+        invoke_signed(
+            &redeem_ix,
+            redeem_infos,
+            &[&[b"protocol_vault", &[vault_bump]]],
+        )?;
+
+        // 5) Credited yield stays in protocol_usdc_account until claimed; pay the
+        //    keeper and the treasury
+        let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[vault_bump]];
+        let binding = [vault_seeds];
+        let fees = [
+            (self.keeper_usdc_account.to_account_info(), keeper_fee),
+            (self.treasury.to_account_info(), protocol_fee),
+        ];
+        for (to, amount) in fees {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_accounts = Transfer {
+                from: self.protocol_usdc_account.to_account_info(),
+                to,
+                authority: self.protocol_vault.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                cpi_accounts,
+                &binding
+            );
+            transfer(cpi_ctx, amount)?;
+        }
+
+        Ok(())
+    }
+}
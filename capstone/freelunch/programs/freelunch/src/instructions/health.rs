@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::{CollateralToppedUp, PaymentAtRisk};
+use crate::solend::{deposit_apy_bps, load_reserve};
+
+/// Whether a PoF's collateral still pays it off on time, returned via return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CollateralHealth {
+    pub due_at: i64,
+    pub projected_payoff_at: Option<i64>, // None if the collateral earns nothing at current APY
+    pub locked_collateral: u64,
+    pub required_collateral: Option<u64>, // Enough to pay by due_at; None once overdue or without a usable APY
+    pub healthy: bool,
+}
+
+#[derive(Accounts)]
+pub struct TopUpCollateral<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: Account<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        constraint = proof_of_payment.buyer == buyer.key() @ ErrorCode::Unauthorized
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> TopUpCollateral<'info> {
+    /// Moves `amount` of the buyer's unlockable stake into the PoF's lock so it
+    /// earns faster. It is released with the rest of the PoF's collateral.
+    pub fn top_up_collateral(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidPurchaseAmount);
+
+        // Settle the yield earned on the old lock before it grows
+        self.proof_of_payment.accrue_yield(self.protocol_vault.yield_index)?;
+        self.proof_of_payment.top_up(amount)?;
+        self.buyer_account.lock_collateral(amount)?;
+        self.protocol_vault.lock_collateral(amount)?;
+
+        emit!(CollateralToppedUp {
+            proof_of_payment: self.proof_of_payment.key(),
+            buyer: self.buyer.key(),
+            amount,
+            locked_collateral: self.proof_of_payment.locked_collateral,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CheckHealth<'info> {
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
+    pub solend_reserve: AccountInfo<'info>,
+}
+
+impl<'info> CheckHealth<'info> {
+    /// Permissionless: projects the PoF's payoff at the current conservative APY and
+    /// emits `PaymentAtRisk` if it lands after the due date.
+    pub fn check_health(&self) -> Result<CollateralHealth> {
+        let proof = &self.proof_of_payment;
+        proof.require_payable()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let reserve = load_reserve(&self.solend_reserve)?;
+        // An APY below the floor counts as earning nothing
+        let apy_bps = self.protocol_vault.conservative_apy_bps(deposit_apy_bps(&reserve)?).ok();
+
+        let due_at = proof.due_at();
+        let (projected_payoff_at, required_collateral) = match apy_bps {
+            Some(apy_bps) => (proof.projected_payoff_at(now, apy_bps)?, proof.required_collateral(now, apy_bps)?),
+            None => (None, None),
+        };
+        let healthy = projected_payoff_at.is_some_and(|payoff_at| payoff_at <= due_at);
+
+        if !healthy {
+            emit!(PaymentAtRisk {
+                proof_of_payment: proof.key(),
+                buyer: proof.buyer,
+                merchant: proof.merchant,
+                payment_number: proof.payment_number,
+                due_at,
+                projected_payoff_at,
+                shortfall: required_collateral.map(|required| required.saturating_sub(proof.locked_collateral)),
+            });
+        }
+
+        Ok(CollateralHealth {
+            due_at,
+            projected_payoff_at,
+            locked_collateral: proof.locked_collateral,
+            required_collateral,
+            healthy,
+        })
+    }
+}
@@ -1,5 +1,12 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
 use crate::state::*;
+use crate::error::ErrorCode;
+use crate::solend::load_reserve;
+use crate::constants::{
+    DEFAULT_APY_CAP_BPS, DEFAULT_APY_FLOOR_BPS, DEFAULT_KEEPER_FEE_BPS, DEFAULT_MAX_PURCHASE_AMOUNT,
+    DEFAULT_MIN_BUFFER_BPS, DEFAULT_PROTOCOL_FEE_BPS,
+};
 
 #[derive(Accounts)]
 pub struct Initialize<'info> {
@@ -15,17 +22,90 @@ pub struct Initialize<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
 
+    // USDC; must be the liquidity mint of the reserve
+    pub usdc_mint: Account<'info, Mint>,
+
+    // Protocol fees accumulate here until the admin withdraws them
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"treasury"],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = protocol_vault,
+    )]
+    pub treasury: Account<'info, TokenAccount>,
+
+    // Redeemed USDC passes through here; pinned so later instructions can't swap it out.
+    // Settlement swaps hand it to the AMM as the vault's USDC ATA, so it must be that ATA.
+    #[account(
+        associated_token::mint = usdc_mint,
+        associated_token::authority = protocol_vault,
+    )]
+    pub protocol_usdc_account: Account<'info, TokenAccount>,
+
+    // cUSDC held for all stakers; its mint is checked against the reserve below
+    #[account(
+        token::authority = protocol_vault,
+    )]
+    pub protocol_collateral_account: Account<'info, TokenAccount>,
+
+    /// CHECK: The lending program; only its key is stored
+    #[account(executable)]
+    pub solend_program: AccountInfo<'info>,
+
+    /// CHECK: Deserialized in init and checked against usdc_mint
+    #[account(owner = solend_program.key() @ ErrorCode::InvalidReserve)]
+    pub solend_reserve: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Initialize<'info> {
+    /// Creates the vault and pins the Solend reserve and the vault's token accounts
+    /// every later instruction must use.
     pub fn init(&mut self, bumps: &InitializeBumps) -> Result<()> {
+        let reserve = load_reserve(&self.solend_reserve)?;
+        require_keys_eq!(reserve.liquidity.mint_pubkey, self.usdc_mint.key(), ErrorCode::InvalidReserve);
+        require_keys_eq!(
+            self.protocol_collateral_account.mint,
+            reserve.collateral.mint_pubkey,
+            ErrorCode::InvalidProtocolAccount
+        );
+
         self.protocol_vault.set_inner(
             ProtocolVault {
                 admin: *self.admin.key,
+                pending_admin: None,
+                paused: false,
+                arbiter: *self.admin.key,
+                usdc_mint: self.usdc_mint.key(),
+                solend_program: self.solend_program.key(),
+                solend_reserve: self.solend_reserve.key(),
+                reserve_liquidity_supply: reserve.liquidity.supply_pubkey,
+                reserve_collateral_mint: reserve.collateral.mint_pubkey,
+                lending_market: reserve.lending_market,
+                usdc_account: self.protocol_usdc_account.key(),
+                collateral_account: self.protocol_collateral_account.key(),
                 total_staked: 0,
+                total_locked: 0,
                 total_rewards: 0,
+                yield_index: 0,
+                allotted_yield: 0,
                 pending_payments: 0,
+                buyer_accounts_opened: 0,
+                keeper_fee_bps: DEFAULT_KEEPER_FEE_BPS,
+                protocol_fee_bps: DEFAULT_PROTOCOL_FEE_BPS,
+                total_protocol_fees: 0,
+                treasury_bump: bumps.treasury,
+                min_buffer_bps: DEFAULT_MIN_BUFFER_BPS,
+                max_purchase_amount: DEFAULT_MAX_PURCHASE_AMOUNT,
+                apy_floor_bps: DEFAULT_APY_FLOOR_BPS,
+                apy_cap_bps: DEFAULT_APY_CAP_BPS,
+                use_smoothed_apy: false,
+                smoothed_apy_bps: 0,
+                apy_updated_at: 0,
                 bump: bumps.protocol_vault,
             }
         );
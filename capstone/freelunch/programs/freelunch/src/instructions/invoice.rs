@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::{InvoiceAccepted, InvoiceCancelled, InvoiceCreated};
+use crate::instructions::open_proof_of_payment;
+
+#[derive(Accounts)]
+pub struct CreateInvoice<'info> {
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+
+    // Invoices are numbered from the same counter as PoFs
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant.key().as_ref()],
+        bump,
+        constraint = merchant_account.status == 1 @ ErrorCode::InvalidMerchant
+    )]
+    pub merchant_account: Account<'info, MerchantAccount>,
+
+    #[account(
+        init,
+        payer = merchant,
+        space = Invoice::INIT_SPACE + 8,
+        seeds = [b"invoice", merchant.key().as_ref(), merchant_account.payment_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateInvoice<'info> {
+    pub fn create_invoice(&mut self, amount: u64, memo_hash: [u8; 32], expires_at: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(amount > 0 && expires_at > now, ErrorCode::InvalidInvoice);
+
+        let merchant_account = &mut self.merchant_account;
+        self.invoice.set_inner(Invoice {
+            merchant: merchant_account.merchant,
+            invoice_number: merchant_account.payment_number,
+            amount,
+            memo_hash,
+            created_at: now,
+            expires_at,
+        });
+        merchant_account.payment_number += 1;
+
+        emit!(InvoiceCreated {
+            invoice: self.invoice.key(),
+            merchant: self.invoice.merchant,
+            invoice_number: self.invoice.invoice_number,
+            amount,
+            memo_hash,
+            expires_at,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct CancelInvoice<'info> {
+    // The merchant, or anyone once the invoice has expired
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = merchant,
+        seeds = [b"invoice", invoice.merchant.as_ref(), invoice.invoice_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    // The merchant paid for the invoice and gets the rent back
+    #[account(
+        mut,
+        address = invoice.merchant @ ErrorCode::InvalidMerchant
+    )]
+    pub merchant: SystemAccount<'info>,
+}
+
+impl<'info> CancelInvoice<'info> {
+    /// The merchant can withdraw an open invoice at any time. Expired invoices can
+    /// be cleaned up by anyone.
+    pub fn cancel_invoice(&mut self) -> Result<()> {
+        let expired = self.invoice.is_expired(Clock::get()?.unix_timestamp);
+        require!(
+            expired || self.authority.key() == self.invoice.merchant,
+            ErrorCode::Unauthorized
+        );
+
+        emit!(InvoiceCancelled {
+            invoice: self.invoice.key(),
+            merchant: self.invoice.merchant,
+            invoice_number: self.invoice.invoice_number,
+            expired,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct AcceptInvoice<'info> {
+    // The buyer accepts directly and pays for the PoF
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref()],
+        bump,
+        constraint = buyer_account.staked_amount > 0 @ ErrorCode::InsufficientStake
+    )]
+    pub buyer_account: Account<'info, BuyerAccount>,
+
+    // Accepting consumes the invoice; the PoF takes over its number
+    #[account(
+        mut,
+        close = merchant,
+        seeds = [b"invoice", invoice.merchant.as_ref(), invoice.invoice_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = ProofOfFuturePayment::INIT_SPACE + 8,
+        seeds = [b"proof_of_payment", buyer.key().as_ref(), invoice.merchant.as_ref(), invoice.invoice_number.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+
+    #[account(
+        seeds = [b"merchant", invoice.merchant.as_ref()],
+        bump
+    )]
+    pub merchant_account: Account<'info, MerchantAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    // Receives the invoice's rent
+    #[account(
+        mut,
+        address = invoice.merchant @ ErrorCode::InvalidMerchant
+    )]
+    pub merchant: SystemAccount<'info>,
+
+    /// The Solend reserve account holding interest rate data
+    #[account(
+        address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve
+    )]
+    /// CHECK: This is solend reserve, pinned at init
+    pub solend_reserve: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AcceptInvoice<'info> {
+    /// Opens a PoF for the invoiced amount, sized and collateralized exactly like an
+    /// admin-relayed purchase. The buyer's signature stands in for a purchase intent.
+    pub fn accept_invoice(&mut self, buffer_bps: u64, installments: Vec<Installment>) -> Result<()> {
+        require!(
+            !self.invoice.is_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::InvoiceExpired
+        );
+
+        open_proof_of_payment(
+            &mut self.proof_of_payment,
+            &mut self.buyer_account,
+            &self.merchant_account,
+            &mut self.protocol_vault,
+            &self.solend_reserve,
+            self.buyer.key(),
+            self.invoice.invoice_number,
+            self.invoice.amount,
+            buffer_bps,
+            installments,
+        )?;
+
+        emit!(InvoiceAccepted {
+            invoice: self.invoice.key(),
+            proof_of_payment: self.proof_of_payment.key(),
+            buyer: self.buyer.key(),
+            merchant: self.invoice.merchant,
+            invoice_number: self.invoice.invoice_number,
+        });
+        Ok(())
+    }
+}
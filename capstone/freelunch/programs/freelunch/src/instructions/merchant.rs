@@ -1,5 +1,8 @@
 use anchor_lang::prelude::*;
+use amm::state::Config;
 use crate::state::*;
+use crate::error::ErrorCode;
+use crate::events::MerchantInitialized;
 
 #[derive(Accounts)]
 pub struct MerchantInit<'info> {
@@ -27,9 +30,56 @@ impl<'info> MerchantInit<'info> {
             payment_number: 0,
             amount_transacted: 0,
             seed,
+            settlement_mint: None,
+            settlement_pool: None,
         }
       );
-      
+
+      emit!(MerchantInitialized {
+          merchant: self.merchant_account.merchant,
+          seed,
+      });
       Ok(())
     }
 }
+
+#[derive(Accounts)]
+pub struct SetSettlementPreference<'info> {
+    pub merchant: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"merchant", merchant.key().as_ref()],
+        bump
+    )]
+    pub merchant_account: Account<'info, MerchantAccount>,
+
+    // The AMM pool payouts are swapped through; required unless settling in USDC
+    pub settlement_pool: Option<Account<'info, Config>>,
+}
+
+impl<'info> SetSettlementPreference<'info> {
+    /// `None` settles in USDC. Otherwise payouts are swapped into `settlement_mint`
+    /// through `settlement_pool`, which must trade that mint.
+    pub fn set_settlement_preference(&mut self, settlement_mint: Option<Pubkey>) -> Result<()> {
+        let merchant_account = &mut self.merchant_account;
+
+        match settlement_mint {
+            None => {
+                merchant_account.settlement_mint = None;
+                merchant_account.settlement_pool = None;
+            }
+            Some(mint) => {
+                let pool = self.settlement_pool.as_ref().ok_or(ErrorCode::InvalidSettlementPool)?;
+                require!(
+                    pool.mint_x == mint || pool.mint_y == mint,
+                    ErrorCode::InvalidSettlementPool
+                );
+                merchant_account.settlement_mint = Some(mint);
+                merchant_account.settlement_pool = Some(pool.key());
+            }
+        }
+
+        Ok(())
+    }
+}
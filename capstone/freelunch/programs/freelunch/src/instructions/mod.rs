@@ -5,6 +5,18 @@ pub mod merchant;
 pub mod purchase;
 pub mod fulfill_payment;
 pub mod claim;
+pub mod cancel;
+pub mod dispute;
+pub mod solvency;
+pub mod harvest;
+pub mod schedule;
+pub mod apy;
+pub mod admin;
+pub mod summary;
+pub mod invoice;
+pub mod health;
+pub mod factoring;
+pub mod close;
 
 pub use init::*;
 pub use stake::*;
@@ -13,3 +25,15 @@ pub use merchant::*;
 pub use purchase::*;
 pub use fulfill_payment::*;
 pub use claim::*;
+pub use cancel::*;
+pub use dispute::*;
+pub use solvency::*;
+pub use harvest::*;
+pub use schedule::*;
+pub use apy::*;
+pub use admin::*;
+pub use summary::*;
+pub use invoice::*;
+pub use health::*;
+pub use factoring::*;
+pub use close::*;
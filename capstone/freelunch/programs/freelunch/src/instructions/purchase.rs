@@ -1,19 +1,25 @@
 use anchor_lang::prelude::*;
-use solend_sdk::math::{Decimal, TryAdd, TryDiv, TryMul, TrySub, WAD};
-use solend_sdk::solana_program::program_pack::Pack;
-use solend_sdk::state::Reserve;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+use anchor_instruction_sysvar::Ed25519InstructionSignatures;
 use crate::state::*;
 use crate::error::ErrorCode;
+use crate::events::ProofOfPaymentCreated;
+use crate::solend::{deposit_apy_bps, load_reserve};
 
 #[derive(Accounts)]
 pub struct CreateProofOfPayment<'info> {
-    // The admin verifies the purchase off-chain and signs to create PoF
+    // The admin relays the buyer-signed purchase intent and pays for the PoF
     #[account(mut)]
     pub admin: Signer<'info>,
 
     // The buyer's staking account
     #[account(
         mut,
+        seeds = [b"buyer", buyer_account.buyer.key().as_ref()],
+        bump,
         constraint = buyer_account.staked_amount > 0 @ ErrorCode::InsufficientStake
     )]
     pub buyer_account: Account<'info, BuyerAccount>,
@@ -36,193 +42,206 @@ pub struct CreateProofOfPayment<'info> {
     )]
     pub merchant_account: Account<'info, MerchantAccount>,
 
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
     // The merchant's account (just for verification)
-    #[account()]
+    #[account(
+        address = merchant_account.merchant @ ErrorCode::InvalidMerchant
+    )]
     pub merchant: SystemAccount<'info>,
 
     /// The Solend reserve account holding interest rate data
-    #[account(mut)]
-    /// CHECK: This is solend reserve
+    #[account(
+        address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve
+    )]
+    /// CHECK: This is solend reserve, pinned at init
     pub solend_reserve: AccountInfo<'info>,
 
+    #[account(
+        address = anchor_lang::solana_program::sysvar::instructions::ID
+    )]
+    /// CHECK: This is the instructions sysvar
+    pub instruction_sysvar: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> CreateProofOfPayment<'info> {
+    /// Checks that the instruction right before this one is an Ed25519 signature by the
+    /// buyer over the purchase intent, then consumes the intent's nonce.
+    pub fn verify_purchase_intent(
+        &mut self,
+        purchase_amount: u64,
+        buffer_bps: u64,
+        nonce: u64,
+        expiry: i64,
+        installments: &[Installment]
+    ) -> Result<()> {
+        require!(Clock::get()?.unix_timestamp <= expiry, ErrorCode::PurchaseIntentExpired);
+        require_eq!(nonce, self.buyer_account.purchase_nonce, ErrorCode::InvalidNonce);
+
+        let intent = PurchaseIntent {
+            buyer: self.buyer_account.buyer,
+            account_number: self.buyer_account.account_number,
+            merchant: self.merchant_account.merchant,
+            amount: purchase_amount,
+            buffer_bps,
+            nonce,
+            expiry,
+            installments: installments.to_vec(),
+        };
+
+        // The Ed25519 instruction must immediately precede this one
+        let current_index = load_current_index_checked(&self.instruction_sysvar.to_account_info())?;
+        require!(current_index > 0, ErrorCode::InvalidPurchaseIntent);
+        let ix = load_instruction_at_checked(
+            current_index as usize - 1,
+            &self.instruction_sysvar.to_account_info()
+        )?;
+        require_keys_eq!(ix.program_id, ed25519_program::ID, ErrorCode::InvalidPurchaseIntent);
+        require_eq!(ix.accounts.len(), 0, ErrorCode::InvalidPurchaseIntent);
+
+        let signatures = Ed25519InstructionSignatures::unpack(&ix.data)?.0;
+        require_eq!(signatures.len(), 1, ErrorCode::InvalidPurchaseIntent);
+        let signature = &signatures[0];
+
+        require!(signature.is_verifiable, ErrorCode::InvalidPurchaseIntent);
+        require_keys_eq!(
+            signature.public_key.ok_or(ErrorCode::InvalidPurchaseIntent)?,
+            intent.buyer,
+            ErrorCode::InvalidPurchaseIntent
+        );
+        require!(
+            signature.message.as_ref().ok_or(ErrorCode::InvalidPurchaseIntent)?.eq(&intent.to_slice()),
+            ErrorCode::InvalidPurchaseIntent
+        );
+
+        self.buyer_account.purchase_nonce = nonce
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
     // create proof of payment
     pub fn purchase(
         &mut self,
         purchase_amount: u64,   // e.g. 5 USDC
-        buffer_bps: u64         // e.g. 500 for an extra 5% buffer
+        buffer_bps: u64,        // e.g. 500 for an extra 5% buffer
+        installments: Vec<Installment> // optional payment plan, empty for a lump sum
     ) -> Result<()> {
-        let solend_reserve_data = self.solend_reserve.data.borrow();
-        let reserve: Reserve = Reserve::unpack(&solend_reserve_data)
-            .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-        // Derive deposit APY from reserve fields
-        let deposit_apy_bps = Self::compute_deposit_apy_bps(&reserve)?;
-
-
-        let buyer_account = &mut self.buyer_account;
-        let merchant_account = &mut self.merchant_account;
-        let proof = &mut self.proof_of_payment;
-
-        require!(merchant_account.status == 1, ErrorCode::InvalidMerchant);
-        require!(purchase_amount > 0, ErrorCode::InvalidPurchaseAmount);
-
-        // 1. Calculate base locked collateral based on APY
-        // locked_value = purchase_amount * 10000 / deposit_apy_bps
-        require!(deposit_apy_bps > 0, ErrorCode::InvalidAPY);
-
-        let base_locked_value = purchase_amount
-            .checked_mul(10000)
-            .ok_or(ErrorCode::Unauthorized)?
-            .checked_div(deposit_apy_bps) // APY derived from reserve
-            .ok_or(ErrorCode::Unauthorized)?;
-
-        // 2. Add buffer
-        // locked_value = base_locked_value * (10000 + buffer_bps) / 10000
-        let locked_value_with_buffer = base_locked_value
-            .checked_mul(10000 + buffer_bps)
-            .ok_or(ErrorCode::Unauthorized)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::Unauthorized)?;
-
-        // Ensure the buyer has enough unlockable funds
-        require!(buyer_account.unlockable_amount >= locked_value_with_buffer, ErrorCode::InsufficientFunds);
-
-        // 3. Lock that collateral
-        buyer_account.unlockable_amount = buyer_account.unlockable_amount
-            .checked_sub(locked_value_with_buffer)
-            .ok_or(ErrorCode::InsufficientFunds)?;
-        buyer_account.locked_amount = buyer_account.locked_amount
-            .checked_add(locked_value_with_buffer)
-            .ok_or(ErrorCode::Unauthorized)?;
-
-        // 4. Fill out the proof of payment
-        proof.payment_amount = purchase_amount; // e.g. 5 USDC
-        proof.locked_collateral = locked_value_with_buffer;  // e.g. 52 or 53 USDC w/ buffer
-        proof.admin = *self.admin.key;
-        proof.buyer = buyer_account.buyer;
-        proof.merchant = merchant_account.merchant;
-        proof.completed = 0; // 0 => not paid, 1 => completed
-        proof.payment_number = merchant_account.payment_number;
-        proof.amount_fulfilled = 0;
-
-        // 5. Increment the merchant's payment_number
-        merchant_account.payment_number += 1;
+        open_proof_of_payment(
+            &mut self.proof_of_payment,
+            &mut self.buyer_account,
+            &self.merchant_account,
+            &mut self.protocol_vault,
+            &self.solend_reserve,
+            self.admin.key(),
+            self.merchant_account.payment_number,
+            purchase_amount,
+            buffer_bps,
+            installments,
+        )?;
+
+        // Increment the merchant's payment_number
+        self.merchant_account.payment_number += 1;
         Ok(())
     }
-
-    /// Use the fields from `reserve` to derive deposit APY. 
-    /// Often deposit_apy ~ utilization * borrow_rate * (1 - protocol_cut).
-    // fn compute_deposit_apy_bps(reserve: &Reserve) -> Result<u64> {
-    //     // 1) Compute utilization = borrowed_amount / (borrowed_amount + available_amount)
-    //     // 2) Compute borrow_rate from config fields, e.g. `reserve.current_borrow_rate()`
-    //     // 3) deposit_apy = borrow_rate * utilization * (1 - protocol_take_rate)
-    //     // Convert to basis points.
-        
-    //     let total_supply = reserve.liquidity.borrowed_amount_wads // as Decimal
-    //         .try_add(Decimal::from(reserve.liquidity.available_amount))
-    //         .map_err(|_| ErrorCode::Unauthorized)?;
-
-    //     if total_supply == Decimal::zero() {
-    //         // no liquidity => APY is zero or negligible
-    //         return Ok(0);
-    //     }
-
-    //     let utilization = reserve.liquidity.borrowed_amount_wads
-    //         .try_div(total_supply)
-    //         .map_err(|_| ErrorCode::Unauthorized)?;
-
-    //     // This is simplified. The real logic is in `reserve.current_borrow_rate()`
-    //     // But let's pretend we do something like:
-    //     let borrow_rate_pct = reserve.config.optimal_borrow_rate; // e.g. 10 => 10%
-    //     let borrow_rate = Decimal::from(borrow_rate_pct as u64).try_div(Decimal::from(100u64))
-    //         .map_err(|_| ErrorCode::Unauthorized)?;
-        
-    //     let protocol_take_rate = Decimal::from(reserve.config.protocol_take_rate as u64)
-    //         .try_div(Decimal::from(100u64))
-    //         .map_err(|_| ErrorCode::Unauthorized)?;
-
-    //     // deposit_apy (decimal) = utilization * borrow_rate * (1 - protocol_take_rate)
-    //     let deposit_apy_decimal = utilization
-    //         .try_mul(borrow_rate)
-    //         .map_err(|_| ErrorCode::Unauthorized)?
-    //         .try_mul(Decimal::one().try_sub(protocol_take_rate)?)
-    //         .map_err(|_| ErrorCode::Unauthorized)?;
-
-    //     // Convert decimal to basis points (1.0 -> 10000 bps)
-    //     let deposit_apy_bps = deposit_apy_decimal
-    //         .to_scaled_val()
-    //         .map_err(|_| ErrorCode::Unauthorized)?
-    //         .checked_div((WAD / 10_000).into())
-    //         .ok_or(ErrorCode::Unauthorized)?;
-        
-    //     Ok(deposit_apy_bps.try_into().unwrap())
-    // }
-
-
-fn compute_deposit_apy_bps(reserve: &Reserve) -> Result<u64> {
-    // 1) Get the current borrow rate from the Solend `Reserve`.
-    //    This usually returns a decimal representing the annualized borrow interest rate (e.g. 0.10 for 10%).
-    let current_borrow_rate = reserve
-        .current_borrow_rate()
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    // 2) Compute utilization = borrowed_amount / (borrowed_amount + available_amount)
-    //    Alternatively, if the SDK provides `reserve.liquidity.utilization_rate()`, you can use that.
-    let borrowed_amount_wads = reserve.liquidity.borrowed_amount_wads;
-    let available_amount = Decimal::from(reserve.liquidity.available_amount);
-
-    let total_supply = borrowed_amount_wads
-        .try_add(available_amount)
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    if total_supply == Decimal::zero() {
-        // No liquidity => APY is zero
-        return Ok(0);
-    }
-
-    // utilization_rate() is often included in the Solend SDK, but we’ll do it manually for clarity:
-    let utilization = borrowed_amount_wads
-        .try_div(total_supply)
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    // 3) Convert protocol take rate to decimal
-    let protocol_take_rate = Decimal::from(reserve.config.protocol_take_rate as u64)
-        .try_div(Decimal::from(100u64))
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    // 4) deposit_apy = current_borrow_rate * utilization * (1 - protocol_take_rate)
-    //    This is the standard approximate formula in Solend for deposit APY from borrow rate.
-    let deposit_apy_decimal = Decimal::from(current_borrow_rate)
-        .try_mul(utilization)
-        .map_err(|_| error!(ErrorCode::Unauthorized))?
-        .try_mul(Decimal::one().try_sub(protocol_take_rate)?)
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    // 5) Convert that decimal deposit APY to basis points:
-    //    e.g., if deposit_apy_decimal = 0.08 => 8% => 800 bps
-    //    We multiply by 10,000.0. Because we’re dealing with WAD decimals,
-    //    we need to carefully scale the result.
-    let deposit_apy_raw = deposit_apy_decimal
-        .to_scaled_val() // e.g. deposit_apy_decimal * WAD
-        .map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    // deposit_apy_raw is the deposit APY in WAD format, so we divide by WAD again 
-    // but then multiply by 10,000 for basis points. 
-    // Alternatively, you can do it as a single combined ratio.
-    let deposit_apy_bps = deposit_apy_raw
-        .checked_div(WAD.into()) // convert from WAD-based decimal to float-like decimal
-        .ok_or(error!(ErrorCode::Unauthorized))?
-        .checked_mul(10_000)
-        .ok_or(error!(ErrorCode::Unauthorized))?;
-
-    // 6) Convert deposit_apy_bps to u64
-    let deposit_apy_bps_u64: u64 = deposit_apy_bps.try_into().map_err(|_| error!(ErrorCode::Unauthorized))?;
-
-    Ok(deposit_apy_bps_u64)
 }
+
+/// Sizes the collateral for `purchase_amount` from the conservative deposit APY plus
+/// `buffer_bps`, locks it from the buyer's stake and fills out `proof` as the
+/// merchant's payment `payment_number`. `payer` paid for the PoF account and gets
+/// its rent back on cancel.
+#[allow(clippy::too_many_arguments)]
+pub fn open_proof_of_payment(
+    proof: &mut Account<ProofOfFuturePayment>,
+    buyer_account: &mut BuyerAccount,
+    merchant_account: &MerchantAccount,
+    protocol_vault: &mut ProtocolVault,
+    solend_reserve: &AccountInfo,
+    payer: Pubkey,
+    payment_number: u64,
+    purchase_amount: u64,
+    buffer_bps: u64,
+    installments: Vec<Installment>,
+) -> Result<()> {
+    require!(buffer_bps >= protocol_vault.min_buffer_bps, ErrorCode::BufferTooLow);
+
+    // Derive deposit APY from reserve fields, bounded by the protocol's APY safeguards
+    let reserve = load_reserve(solend_reserve)?;
+    let spot_apy_bps = deposit_apy_bps(&reserve)?;
+    let deposit_apy_bps = protocol_vault.conservative_apy_bps(spot_apy_bps)?;
+
+    require!(merchant_account.status == 1, ErrorCode::InvalidMerchant);
+    require!(purchase_amount > 0, ErrorCode::InvalidPurchaseAmount);
+    require!(purchase_amount <= protocol_vault.max_purchase_amount, ErrorCode::PurchaseTooLarge);
+    ProofOfFuturePayment::validate_schedule(&installments, purchase_amount)?;
+
+    // 1. Calculate base locked collateral based on APY
+    // locked_value = purchase_amount * 10000 / deposit_apy_bps
+    let base_locked_value = purchase_amount
+        .checked_mul(10000)
+        .ok_or(ErrorCode::Unauthorized)?
+        .checked_div(deposit_apy_bps) // conservative APY, never zero
+        .ok_or(ErrorCode::Unauthorized)?;
+
+    // 2. Add buffer
+    // locked_value = base_locked_value * (10000 + buffer_bps) / 10000
+    let locked_value_with_buffer = base_locked_value
+        .checked_mul(10000 + buffer_bps)
+        .ok_or(ErrorCode::Unauthorized)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::Unauthorized)?;
+
+    // Ensure the buyer has enough unlockable funds
+    require!(buyer_account.unlockable_amount >= locked_value_with_buffer, ErrorCode::InsufficientFunds);
+
+    // 3. Lock that collateral and count the purchase against the buyer's credit limit
+    buyer_account.lock_collateral(locked_value_with_buffer)?;
+    buyer_account.open_position(purchase_amount)?;
+
+    // 4. Fill out the proof of payment
+    proof.set_inner(ProofOfFuturePayment {
+        payment_amount: purchase_amount, // e.g. 5 USDC
+        locked_collateral: locked_value_with_buffer, // e.g. 52 or 53 USDC w/ buffer
+        admin: payer,
+        buyer: buyer_account.buyer,
+        merchant: merchant_account.merchant,
+        status: PaymentStatus::Pending,
+        payment_number,
+        amount_fulfilled: 0,
+        created_at: Clock::get()?.unix_timestamp,
+        installments,
+        current_installment: 0,
+        earned_unclaimed: 0,
+        factored: false,
+        factored_at: 0,
+        factoring_discount: 0,
+        yield_index: protocol_vault.yield_index, // Only yield allotted from now on is this PoF's
+        allotted_yield: 0,
+    });
+
+    // 5. Track the locked collateral and the new outstanding liability
+    protocol_vault.lock_collateral(locked_value_with_buffer)?;
+    protocol_vault.add_liability(purchase_amount)?;
+
+    emit!(ProofOfPaymentCreated {
+        proof_of_payment: proof.key(),
+        buyer: proof.buyer,
+        merchant: proof.merchant,
+        payment_number: proof.payment_number,
+        payment_amount: purchase_amount,
+        apy_bps: deposit_apy_bps,
+        buffer_bps,
+        locked_collateral: locked_value_with_buffer,
+        installments: proof.installments.len() as u8,
+    });
+    Ok(())
 }
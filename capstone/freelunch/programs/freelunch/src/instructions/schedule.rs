@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+
+/// A PoF's installment plan and progress, returned via return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InstallmentSchedule {
+    pub installments: Vec<Installment>,
+    pub current_installment: u8,
+    pub amount_fulfilled: u64,
+    pub next_due_at: Option<i64>, // None once every installment has cleared
+}
+
+#[derive(Accounts)]
+pub struct Schedule<'info> {
+    pub proof_of_payment: Account<'info, ProofOfFuturePayment>,
+}
+
+impl<'info> Schedule<'info> {
+    pub fn installment_schedule(&self) -> Result<InstallmentSchedule> {
+        let proof = &self.proof_of_payment;
+
+        Ok(InstallmentSchedule {
+            installments: proof.installments.clone(),
+            current_installment: proof.current_installment,
+            amount_fulfilled: proof.amount_fulfilled,
+            next_due_at: proof.current().map(|installment| installment.due_at),
+        })
+    }
+}
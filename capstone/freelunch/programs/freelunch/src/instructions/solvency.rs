@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+
+/// Global protocol totals, returned to the caller via return data.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolvencyReport {
+    pub total_staked: u64,     // Staked principal
+    pub total_locked: u64,     // Principal locked as PoF collateral
+    pub total_unlocked: u64,   // Principal buyers can withdraw
+    pub pending_payments: u64, // Outstanding PoF liabilities
+    pub total_rewards: u64,    // Yield harvested so far
+    pub total_protocol_fees: u64, // Share of that yield sent to the treasury
+}
+
+#[derive(Accounts)]
+pub struct Solvency<'info> {
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+}
+
+impl<'info> Solvency<'info> {
+    pub fn solvency_report(&self) -> Result<SolvencyReport> {
+        let vault = &self.protocol_vault;
+
+        Ok(SolvencyReport {
+            total_staked: vault.total_staked,
+            total_locked: vault.total_locked,
+            total_unlocked: vault.total_staked
+                .checked_sub(vault.total_locked)
+                .ok_or(ErrorCode::MathOverflow)?,
+            pending_payments: vault.pending_payments,
+            total_rewards: vault.total_rewards,
+            total_protocol_fees: vault.total_protocol_fees,
+        })
+    }
+}
@@ -7,6 +7,9 @@ use solend_sdk::{
 };
 
 use crate::state::{BuyerAccount, ProtocolVault};
+use crate::error::ErrorCode;
+use crate::events::Staked;
+use crate::constants::DEFAULT_MAX_BUYER_EXPOSURE;
 
 #[derive(Accounts)]
 pub struct StakeAsset<'info> {
@@ -14,9 +17,10 @@ pub struct StakeAsset<'info> {
     pub buyer: Signer<'info>,
 
     // The user's USDC token account (holding actual USDC)
-    // TODO: maybe add some constraint here
     #[account(
         mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = buyer,
     )]
     pub buyer_usdc_account: Account<'info, TokenAccount>,
 
@@ -34,26 +38,35 @@ pub struct StakeAsset<'info> {
     #[account(
         mut,
         seeds = [b"protocol_vault"],
-        bump = protocol_vault.bump
+        bump = protocol_vault.bump,
+        constraint = !protocol_vault.paused @ ErrorCode::ProtocolPaused
     )]
     pub protocol_vault: Account<'info, ProtocolVault>,
 
-    // Solends accounts
-    #[account(mut)]
+    // Solend accounts, pinned to the reserve recorded in protocol_vault
     /// CHECK: This is solend program
+    #[account(address = protocol_vault.solend_program @ ErrorCode::InvalidReserve)]
     pub solend_program: AccountInfo<'info>,
-    /// CHECK: This is the Solend Reserve for USDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(mut, address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
     pub solend_reserve: AccountInfo<'info>,
-    /// CHECK: This is the Solend liquidity supply. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend liquidity supply of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_liquidity_supply @ ErrorCode::InvalidReserve)]
     pub reserve_liquidity_supply: AccountInfo<'info>,
-    /// CHECK: This is the Solend collateral mint for cUSDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend collateral mint for cUSDC of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_collateral_mint @ ErrorCode::InvalidReserve)]
     pub reserve_collateral_mint: AccountInfo<'info>,
-    /// CHECK: This is the Solend collateral mint for cUSDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend lending market of the pinned reserve
+    #[account(address = protocol_vault.lending_market @ ErrorCode::InvalidReserve)]
     pub lending_market: AccountInfo<'info>,
-    /// CHECK: This is the Lending Market Authority. Verified via Solend CPI instructions.
+    /// CHECK: This is the Lending Market Authority. Solend derives it from lending_market during the CPI.
     pub lending_market_authority: AccountInfo<'info>,
 
-    // A token account (PDA) to hold the cUSDC on behalf of your program or user
+    // cUSDC held on behalf of all stakers, pinned at init
+    #[account(
+        mut,
+        address = protocol_vault.collateral_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_collateral_account: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
@@ -76,7 +89,7 @@ impl<'info> StakeAsset<'info> {
             self.reserve_liquidity_supply.key(),         // Reserve liquidity supply account
             self.reserve_collateral_mint.key(), // Reserve collateral mint
             self.lending_market.key(),         // Lending market account
-            self.lending_market_authority.key(),         // Lending market authority
+            self.buyer.key(),                  // Transfer authority (Solend derives the market authority)
         );
 
         let account_infos = &[
@@ -98,11 +111,30 @@ impl<'info> StakeAsset<'info> {
             account_infos,
         )?;
     
+        // First stake creates the account; start it at the default credit limit and
+        // give it a number no earlier account of this buyer had
+        if buyer_account.buyer == Pubkey::default() {
+            buyer_account.max_exposure = DEFAULT_MAX_BUYER_EXPOSURE;
+            buyer_account.account_number = protocol_vault.buyer_accounts_opened;
+            protocol_vault.buyer_accounts_opened = protocol_vault.buyer_accounts_opened
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
         buyer_account.buyer = *buyer.key;
-        buyer_account.staked_amount += amount;
-        buyer_account.unlockable_amount += amount;
+        buyer_account.staked_amount = buyer_account.staked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        buyer_account.unlockable_amount = buyer_account.unlockable_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
     
-        protocol_vault.total_staked += amount;
+        protocol_vault.record_stake(amount)?;
+
+        emit!(Staked {
+            buyer: buyer_account.buyer,
+            amount,
+            staked_amount: buyer_account.staked_amount,
+        });
     
         Ok(())
     }
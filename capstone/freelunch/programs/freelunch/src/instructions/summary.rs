@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use crate::state::*;
+use crate::error::ErrorCode;
+use crate::solend::{deposit_apy_bps, load_reserve};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BuyerSummary {
+    pub staked_amount: u64,
+    pub locked_amount: u64,
+    pub unlockable_amount: u64,
+    pub open_pofs: u16,
+    pub outstanding_amount: u64,   // Still owed across open PoFs
+    pub lifetime_volume: u64,
+    pub max_exposure: u64,
+    pub projected_payoff_at: Option<i64>, // When locked collateral's yield covers what is owed; None if it earns nothing
+}
+
+#[derive(Accounts)]
+pub struct Summary<'info> {
+    pub buyer_account: Account<'info, BuyerAccount>,
+
+    #[account(
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Account<'info, ProtocolVault>,
+
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
+    pub solend_reserve: AccountInfo<'info>,
+}
+
+impl<'info> Summary<'info> {
+    pub fn buyer_summary(&self) -> Result<BuyerSummary> {
+        let buyer = &self.buyer_account;
+
+        Ok(BuyerSummary {
+            staked_amount: buyer.staked_amount,
+            locked_amount: buyer.locked_amount,
+            unlockable_amount: buyer.unlockable_amount,
+            open_pofs: buyer.open_pofs,
+            outstanding_amount: buyer.outstanding_amount,
+            lifetime_volume: buyer.lifetime_volume,
+            max_exposure: buyer.max_exposure,
+            projected_payoff_at: self.projected_payoff_at()?,
+        })
+    }
+
+    /// When the buyer's locked collateral pays off everything outstanding, at the
+    /// same APY purchases are sized with.
+    fn projected_payoff_at(&self) -> Result<Option<i64>> {
+        let buyer = &self.buyer_account;
+        let now = Clock::get()?.unix_timestamp;
+        if buyer.outstanding_amount == 0 {
+            return Ok(Some(now));
+        }
+
+        let reserve = load_reserve(&self.solend_reserve)?;
+        let Ok(apy_bps) = self.protocol_vault.conservative_apy_bps(deposit_apy_bps(&reserve)?) else {
+            return Ok(None);
+        };
+        projected_payoff_at(now, buyer.outstanding_amount, buyer.locked_amount, apy_bps)
+    }
+}
@@ -5,6 +5,8 @@ use solend_sdk::solana_program::program::invoke_signed;
 
 use crate::state::*;
 use crate::error::ErrorCode;
+use crate::events::Unstaked;
+use crate::solend::{collateral_for_liquidity, load_reserve};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
@@ -26,30 +28,44 @@ pub struct Withdraw<'info> {
     pub protocol_vault: Account<'info, ProtocolVault>,
 
     /// The protocol’s USDC account (where redeemed USDC goes).
-    #[account(mut)]
+    #[account(
+        mut,
+        address = protocol_vault.usdc_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_usdc_account: Account<'info, TokenAccount>,
 
     /// The buyer’s USDC account to receive the withdrawn USDC.
-    #[account(mut)]
+    #[account(
+        mut,
+        token::mint = protocol_vault.usdc_mint,
+        token::authority = buyer,
+    )]
     pub buyer_usdc_account: Account<'info, TokenAccount>,
 
     /// cUSDC token account (owned by the protocol vault) that holds staked collateral
-    #[account(mut)]
+    #[account(
+        mut,
+        address = protocol_vault.collateral_account @ ErrorCode::InvalidProtocolAccount,
+    )]
     pub protocol_collateral_account: Account<'info, TokenAccount>,
 
-    // Solend + related accounts
-    #[account(mut)]
+    // Solend accounts, pinned to the reserve recorded in protocol_vault
     /// CHECK: This is solend program
+    #[account(address = protocol_vault.solend_program @ ErrorCode::InvalidReserve)]
     pub solend_program: AccountInfo<'info>,
-    /// CHECK: This is the Solend Reserve for USDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend Reserve for USDC, pinned at init
+    #[account(mut, address = protocol_vault.solend_reserve @ ErrorCode::InvalidReserve)]
     pub solend_reserve: AccountInfo<'info>,
-    /// CHECK: This is the Solend liquidity supply. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend liquidity supply of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_liquidity_supply @ ErrorCode::InvalidReserve)]
     pub reserve_liquidity_supply: AccountInfo<'info>,
-    /// CHECK: This is the Solend collateral mint for cUSDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend collateral mint for cUSDC of the pinned reserve
+    #[account(mut, address = protocol_vault.reserve_collateral_mint @ ErrorCode::InvalidReserve)]
     pub reserve_collateral_mint: AccountInfo<'info>,
-    /// CHECK: This is the Solend collateral mint for cUSDC. Verified via Solend CPI instructions.
+    /// CHECK: This is the Solend lending market of the pinned reserve
+    #[account(address = protocol_vault.lending_market @ ErrorCode::InvalidReserve)]
     pub lending_market: AccountInfo<'info>,
-    /// CHECK: This is the Lending Market Authority. Verified via Solend CPI instructions.
+    /// CHECK: This is the Lending Market Authority. Solend derives it from lending_market during the CPI.
     pub lending_market_authority: AccountInfo<'info>,
 
     pub token_program: Program<'info, Token>,
@@ -57,23 +73,23 @@ pub struct Withdraw<'info> {
 
 impl<'info> Withdraw<'info> {
     pub fn unstake(&mut self, amount: u64) -> Result<()> {
-        let buyer_account = &mut self.buyer_account;
-        let protocol_vault: &Account<'info, ProtocolVault> = &self.protocol_vault;
+        let vault_bump = self.protocol_vault.bump;
 
         // 1) Check buyer has enough unlockable
-        require!(buyer_account.unlockable_amount >= amount, ErrorCode::InsufficientFunds);
+        require!(self.buyer_account.unlockable_amount >= amount, ErrorCode::InsufficientFunds);
 
-        // 2) Redeem from Solend cUSDC to USDC
+        // 2) Redeem enough cUSDC from Solend to cover `amount` USDC
+        let reserve = load_reserve(&self.solend_reserve)?;
         let redeem_ix = redeem_reserve_collateral(
             self.solend_program.key(),
-            amount, // This is cUSDC amount to redeem; we assume 1:1
+            collateral_for_liquidity(&reserve, amount)?,
             self.protocol_collateral_account.key(),
             self.protocol_usdc_account.key(),
             self.solend_reserve.key(),
-            self.reserve_liquidity_supply.key(),
             self.reserve_collateral_mint.key(),
+            self.reserve_liquidity_supply.key(),
             self.lending_market.key(),
-            self.lending_market_authority.key(),
+            self.protocol_vault.key(),
         );
 
         let account_infos = &[
@@ -84,6 +100,7 @@ impl<'info> Withdraw<'info> {
             self.reserve_collateral_mint.to_account_info(),
             self.lending_market.to_account_info(),
             self.lending_market_authority.to_account_info(),
+            self.protocol_vault.to_account_info(),
             self.token_program.to_account_info(),
             self.solend_program.to_account_info(),
         ];
@@ -93,7 +110,7 @@ impl<'info> Withdraw<'info> {
         invoke_signed(
             &redeem_ix,
             account_infos,
-            &[&[b"protocol_vault", &[protocol_vault.bump]]],
+            &[&[b"protocol_vault", &[vault_bump]]],
         )?;
 
         // 4) USDC in protocol_usdc_account, do a normal SPL transfer to buyer
@@ -103,7 +120,7 @@ impl<'info> Withdraw<'info> {
             to: self.buyer_usdc_account.to_account_info(),
             authority: self.protocol_vault.to_account_info(),
         };
-        let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[protocol_vault.bump]];
+        let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[vault_bump]];
         let binding = [vault_seeds];
         let cpi_ctx = CpiContext::new_with_signer(
             cpi_program,
@@ -114,14 +131,20 @@ impl<'info> Withdraw<'info> {
 
 
         // 5) Update local BNPL state
+        let buyer_account = &mut self.buyer_account;
         buyer_account.staked_amount = buyer_account.staked_amount.checked_sub(amount)
             .ok_or(ErrorCode::InsufficientFunds)?;
         buyer_account.unlockable_amount = buyer_account.unlockable_amount.checked_sub(amount)
             .ok_or(ErrorCode::InsufficientFunds)?;
 
         // Decrement total_staked in the protocol vault
-         protocol_vault.total_staked.checked_sub(amount)
-        .ok_or(ErrorCode::InsufficientFunds)?;
+        self.protocol_vault.record_unstake(amount)?;
+
+        emit!(Unstaked {
+            buyer: self.buyer_account.buyer,
+            amount,
+            staked_amount: self.buyer_account.staked_amount,
+        });
 
         Ok(())
     }
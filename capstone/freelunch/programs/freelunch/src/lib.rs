@@ -1,8 +1,13 @@
 pub mod instructions;
 pub mod state;
 pub mod error;
+pub mod constants;
+pub mod solend;
+pub mod settlement;
+pub mod events;
 
 pub use instructions::*;
+pub use state::Installment;
 
 use anchor_lang::prelude::*;
 
@@ -33,29 +38,212 @@ pub mod freelunch {
         ctx.accounts.merchant_init(seed)
     }
 
-    /// 5) Create a proof-of-payment (purchase)
+    /// 5) Create a proof-of-payment (purchase) from a buyer-signed purchase intent
     pub fn create_proof_of_payment(
         ctx: Context<CreateProofOfPayment>,
         purchase_amount: u64,
-        buffer_bps: u64
+        buffer_bps: u64,
+        nonce: u64,
+        expiry: i64,
+        installments: Vec<Installment>
     ) -> Result<()> {
-        ctx.accounts.purchase(purchase_amount, buffer_bps)
+        ctx.accounts.verify_purchase_intent(purchase_amount, buffer_bps, nonce, expiry, &installments)?;
+        ctx.accounts.purchase(purchase_amount, buffer_bps, installments)
     }
 
     /// 6) Fulfill an outstanding proof-of-payment with yield (admin or crank usage)
-    pub fn fulfill_proof_of_payment(
-        ctx: Context<FulfillProofOfPayment>,
-        amount_to_pay_now: u64
+    pub fn fulfill_proof_of_payment<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FulfillProofOfPayment<'info>>,
+        amount_to_pay_now: u64,
+        min_amount_out: u64
     ) -> Result<()> {
-        ctx.accounts.complete_payment(amount_to_pay_now)
+        ctx.accounts.complete_payment(amount_to_pay_now, min_amount_out, ctx.remaining_accounts)
     }
 
     /// 7) Merchant claims partial or full payment
-    pub fn merchant_claim(
-        ctx: Context<MerchantClaim>,
-        amount_to_claim: u64
+    pub fn merchant_claim<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MerchantClaim<'info>>,
+        amount_to_claim: u64,
+        min_amount_out: u64
     ) -> Result<()> {
-        ctx.accounts.merchant_claim(amount_to_claim)
+        ctx.accounts.merchant_claim(amount_to_claim, min_amount_out, ctx.remaining_accounts)
     }
 
+    /// 8) Cancel an unfulfilled proof-of-payment and refund the buyer's collateral
+    pub fn cancel_proof_of_payment(ctx: Context<CancelProofOfPayment>) -> Result<()> {
+        ctx.accounts.cancel()
+    }
+
+    /// 9) Buyer disputes a proof-of-payment, freezing fulfillment and claims
+    pub fn open_dispute(ctx: Context<OpenDispute>) -> Result<()> {
+        ctx.accounts.open_dispute()
+    }
+
+    /// 10) Arbiter settles a disputed proof-of-payment
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        resolution: DisputeResolution
+    ) -> Result<()> {
+        ctx.accounts.resolve_dispute(resolution)
+    }
+
+    /// 11) Admin sets the dispute arbiter
+    pub fn set_arbiter(ctx: Context<SetArbiter>, arbiter: Pubkey) -> Result<()> {
+        ctx.accounts.set_arbiter(arbiter)
+    }
+
+    /// 12) Read-only snapshot of the protocol's global accounting
+    pub fn solvency_report(ctx: Context<Solvency>) -> Result<SolvencyReport> {
+        ctx.accounts.solvency_report()
+    }
+
+    /// 13) Permissionless crank: harvest accrued yield and credit it to open proof-of-payments
+    pub fn harvest_and_pay<'info>(
+        ctx: Context<'_, '_, 'info, 'info, HarvestAndPay<'info>>
+    ) -> Result<()> {
+        ctx.accounts.harvest_and_pay(ctx.remaining_accounts)
+    }
+
+    /// 14) Merchant chooses the token payouts are settled in
+    pub fn set_settlement_preference(
+        ctx: Context<SetSettlementPreference>,
+        settlement_mint: Option<Pubkey>
+    ) -> Result<()> {
+        ctx.accounts.set_settlement_preference(settlement_mint)
+    }
+
+    /// 15) Read-only view of a proof-of-payment's installment plan
+    pub fn installment_schedule(ctx: Context<Schedule>) -> Result<InstallmentSchedule> {
+        ctx.accounts.installment_schedule()
+    }
+
+    /// 16) Admin sets the APY floor/cap and whether the smoothed APY is used
+    pub fn update_apy_params(
+        ctx: Context<UpdateApyParams>,
+        apy_floor_bps: u64,
+        apy_cap_bps: u64,
+        use_smoothed_apy: bool
+    ) -> Result<()> {
+        ctx.accounts.update_apy_params(apy_floor_bps, apy_cap_bps, use_smoothed_apy)
+    }
+
+    /// 17) Permissionless crank: fold the reserve's current APY into the smoothed APY
+    pub fn refresh_apy(ctx: Context<RefreshApy>) -> Result<()> {
+        ctx.accounts.refresh_apy()
+    }
+
+    /// 18) Admin proposes a new admin, who must accept before it takes effect
+    pub fn set_admin(ctx: Context<AdminConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.set_admin(new_admin)
+    }
+
+    /// 19) The proposed admin accepts the role
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        ctx.accounts.accept_admin()
+    }
+
+    /// 20) Admin pauses or resumes staking, purchases and payouts
+    pub fn set_paused(ctx: Context<AdminConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.set_paused(paused)
+    }
+
+    /// 21) Admin updates the protocol parameters
+    pub fn update_params(
+        ctx: Context<AdminConfig>,
+        min_buffer_bps: u64,
+        max_purchase_amount: u64,
+        keeper_fee_bps: u16,
+        protocol_fee_bps: u16
+    ) -> Result<()> {
+        ctx.accounts.update_params(min_buffer_bps, max_purchase_amount, keeper_fee_bps, protocol_fee_bps)
+    }
+
+    /// 22) Admin withdraws accumulated protocol fees from the treasury
+    pub fn withdraw_protocol_fees(ctx: Context<WithdrawProtocolFees>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_protocol_fees(amount)
+    }
+
+    /// 23) Admin sets a buyer's maximum outstanding exposure
+    pub fn set_credit_limit(ctx: Context<SetCreditLimit>, max_exposure: u64) -> Result<()> {
+        ctx.accounts.set_credit_limit(max_exposure)
+    }
+
+    /// 24) Read-only view of a buyer's position and projected payoff
+    pub fn buyer_summary(ctx: Context<Summary>) -> Result<BuyerSummary> {
+        ctx.accounts.buyer_summary()
+    }
+
+    /// 25) Merchant issues an invoice a buyer can accept until it expires
+    pub fn create_invoice(
+        ctx: Context<CreateInvoice>,
+        amount: u64,
+        memo_hash: [u8; 32],
+        expires_at: i64
+    ) -> Result<()> {
+        ctx.accounts.create_invoice(amount, memo_hash, expires_at)
+    }
+
+    /// 26) Merchant withdraws an invoice, or anyone closes an expired one
+    pub fn cancel_invoice(ctx: Context<CancelInvoice>) -> Result<()> {
+        ctx.accounts.cancel_invoice()
+    }
+
+    /// 27) Buyer accepts an invoice, creating a proof-of-payment from their stake
+    pub fn accept_invoice(
+        ctx: Context<AcceptInvoice>,
+        buffer_bps: u64,
+        installments: Vec<Installment>
+    ) -> Result<()> {
+        ctx.accounts.accept_invoice(buffer_bps, installments)
+    }
+
+    /// 28) Buyer locks more of their stake behind a proof-of-payment
+    pub fn top_up_collateral(ctx: Context<TopUpCollateral>, amount: u64) -> Result<()> {
+        ctx.accounts.top_up_collateral(amount)
+    }
+
+    /// 29) Permissionless: flag a proof-of-payment whose collateral won't pay it on time
+    pub fn check_health(ctx: Context<CheckHealth>) -> Result<CollateralHealth> {
+        ctx.accounts.check_health()
+    }
+
+    /// 30) Admin creates the factoring pool with its annual discount rate
+    pub fn init_factoring_pool(ctx: Context<InitFactoringPool>, discount_bps: u64) -> Result<()> {
+        ctx.accounts.init_factoring_pool(discount_bps, &ctx.bumps)
+    }
+
+    /// 31) Admin changes the discount charged on newly factored payments
+    pub fn set_factoring_discount(ctx: Context<SetFactoringDiscount>, discount_bps: u64) -> Result<()> {
+        ctx.accounts.set_factoring_discount(discount_bps)
+    }
+
+    /// 32) LP deposits USDC into the factoring pool for shares
+    pub fn deposit_factoring_liquidity(ctx: Context<FactoringLiquidity>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_factoring_liquidity(amount)
+    }
+
+    /// 33) LP redeems shares for idle USDC from the factoring pool
+    pub fn withdraw_factoring_liquidity(ctx: Context<FactoringLiquidity>, shares: u64) -> Result<()> {
+        ctx.accounts.withdraw_factoring_liquidity(shares)
+    }
+
+    /// 34) Merchant sells the rest of a proof-of-payment to the pool for a discounted payout now
+    pub fn merchant_factor_payment(ctx: Context<FactorPayment>, min_payout: u64) -> Result<()> {
+        ctx.accounts.merchant_factor_payment(min_payout)
+    }
+
+    /// 35) Permissionless crank: move a factored proof-of-payment's earned yield into the pool
+    pub fn collect_factored_payment(ctx: Context<CollectFactoredPayment>) -> Result<()> {
+        ctx.accounts.collect_factored_payment()
+    }
+
+    /// 36) Close a finished proof-of-payment and return its rent to whoever paid for it
+    pub fn close_proof_of_payment(ctx: Context<CloseProofOfPayment>) -> Result<()> {
+        ctx.accounts.close_proof_of_payment()
+    }
+
+    /// 37) Buyer closes their emptied staking account
+    pub fn close_buyer_account(ctx: Context<CloseBuyerAccount>) -> Result<()> {
+        ctx.accounts.close_buyer_account()
+    }
 }
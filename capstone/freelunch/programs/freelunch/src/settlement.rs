@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Transfer, transfer};
+use amm::cpi::accounts::Swap;
+use amm::state::Config;
+use crate::state::*;
+use crate::error::ErrorCode;
+
+// A merchant with a settlement mint expects these accounts in `remaining_accounts`:
+// [amm_program, mint_x, mint_y, mint_lp, pool_config, protocol_mint_x_ata,
+//  protocol_mint_y_ata, pool_vault_x, pool_vault_y, system_program,
+//  associated_token_program, merchant_settlement_account]
+// Both protocol ATAs must already exist: the AMM would create missing ones with the
+// swapping user as payer, and the vault PDA carries data so it can't fund them.
+pub const SETTLEMENT_SWAP_ACCOUNTS: usize = 12;
+
+/// Sends `amount` USDC from the protocol to the merchant. If the merchant set a
+/// settlement mint, the USDC is first swapped through their pool and at least
+/// `min_amount_out` of the settlement token is delivered instead.
+#[allow(clippy::too_many_arguments)]
+pub fn pay_merchant<'info>(
+    merchant_account: &MerchantAccount,
+    protocol_vault: &Account<'info, ProtocolVault>,
+    protocol_usdc_account: &Account<'info, TokenAccount>,
+    merchant_usdc_account: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    swap_accounts: &'info [AccountInfo<'info>],
+    amount: u64,
+    min_amount_out: u64,
+) -> Result<()> {
+    let vault_seeds: &[&[u8]] = &[&b"protocol_vault"[..], &[protocol_vault.bump]];
+    let binding = [vault_seeds];
+
+    let (Some(settlement_mint), Some(settlement_pool)) =
+        (merchant_account.settlement_mint, merchant_account.settlement_pool)
+    else {
+        // Plain USDC payout
+        let cpi_accounts = Transfer {
+            from: protocol_usdc_account.to_account_info(),
+            to: merchant_usdc_account.to_account_info(),
+            authority: protocol_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &binding);
+        return transfer(cpi_ctx, amount);
+    };
+
+    let [
+        amm_program,
+        mint_x,
+        mint_y,
+        mint_lp,
+        pool_config,
+        protocol_mint_x_ata,
+        protocol_mint_y_ata,
+        pool_vault_x,
+        pool_vault_y,
+        system_program,
+        associated_token_program,
+        merchant_settlement_account,
+    ] = swap_accounts else {
+        return err!(ErrorCode::InvalidRemainingAccounts);
+    };
+
+    // 1) Only the pool the merchant picked, on the real AMM program
+    require_keys_eq!(amm_program.key(), amm::ID, ErrorCode::InvalidSettlementPool);
+    require_keys_eq!(pool_config.key(), settlement_pool, ErrorCode::InvalidSettlementPool);
+    let pool = Account::<Config>::try_from(pool_config)?;
+
+    // USDC goes in on one side and the settlement mint comes out of the other
+    let usdc_mint = protocol_usdc_account.mint;
+    let (is_x, usdc_ata, settlement_ata) = if pool.mint_x == usdc_mint && pool.mint_y == settlement_mint {
+        (true, protocol_mint_x_ata, protocol_mint_y_ata)
+    } else if pool.mint_y == usdc_mint && pool.mint_x == settlement_mint {
+        (false, protocol_mint_y_ata, protocol_mint_x_ata)
+    } else {
+        return err!(ErrorCode::InvalidSettlementPool);
+    };
+    require_keys_eq!(usdc_ata.key(), protocol_usdc_account.key(), ErrorCode::InvalidSettlementPool);
+    require!(
+        settlement_ata.owner == &anchor_spl::token::ID && !settlement_ata.data_is_empty(),
+        ErrorCode::SettlementAccountMissing
+    );
+
+    let merchant_settlement = Account::<TokenAccount>::try_from(merchant_settlement_account)?;
+    require_keys_eq!(merchant_settlement.owner, merchant_account.merchant, ErrorCode::InvalidSettlementPool);
+    require_keys_eq!(merchant_settlement.mint, settlement_mint, ErrorCode::InvalidSettlementPool);
+
+    // 2) Swap with the protocol vault acting as the AMM user
+    let balance_before = Account::<TokenAccount>::try_from(settlement_ata)?.amount;
+
+    let cpi_accounts = Swap {
+        user: protocol_vault.to_account_info(),
+        mint_x: mint_x.clone(),
+        mint_y: mint_y.clone(),
+        mint_lp: mint_lp.clone(),
+        config: pool_config.clone(),
+        user_mint_x_ata: protocol_mint_x_ata.clone(),
+        user_mint_y_ata: protocol_mint_y_ata.clone(),
+        vault_x: pool_vault_x.clone(),
+        vault_y: pool_vault_y.clone(),
+        system_program: system_program.clone(),
+        token_program: token_program.to_account_info(),
+        associated_token_program: associated_token_program.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(amm_program.clone(), cpi_accounts, &binding);
+    amm::cpi::swap(cpi_ctx, is_x, amount, min_amount_out)?;
+
+    let received = Account::<TokenAccount>::try_from(settlement_ata)?
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(received >= min_amount_out, ErrorCode::SlippageExceeded);
+
+    // 3) Forward the proceeds to the merchant
+    let cpi_accounts = Transfer {
+        from: settlement_ata.clone(),
+        to: merchant_settlement_account.clone(),
+        authority: protocol_vault.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &binding);
+    transfer(cpi_ctx, received)
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use solend_sdk::math::{Decimal, TryAdd, TryDiv, TryMul, TrySub, WAD};
+use solend_sdk::solana_program::program_pack::Pack;
+use solend_sdk::state::Reserve;
+use crate::error::ErrorCode;
+
+// Helpers for reading Solend reserve state. Amounts in USDC are "liquidity",
+// amounts in cUSDC are "collateral".
+
+pub fn load_reserve(solend_reserve: &AccountInfo) -> Result<Reserve> {
+    let data = solend_reserve.data.borrow();
+    Reserve::unpack(&data).map_err(|_| error!(ErrorCode::InvalidReserve))
+}
+
+/// USDC value of `collateral` cUSDC at the reserve's current exchange rate.
+pub fn collateral_to_liquidity(reserve: &Reserve, collateral: u64) -> Result<u64> {
+    reserve
+        .collateral_exchange_rate()
+        .and_then(|rate| rate.collateral_to_liquidity(collateral))
+        .map_err(|_| error!(ErrorCode::InvalidReserve))
+}
+
+/// cUSDC that must be redeemed to receive at least `liquidity` USDC.
+pub fn collateral_for_liquidity(reserve: &Reserve, liquidity: u64) -> Result<u64> {
+    let rate = reserve
+        .collateral_exchange_rate()
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+    let collateral = rate
+        .liquidity_to_collateral(liquidity)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    // liquidity_to_collateral rounds down; round up so the redeem covers `liquidity`
+    let redeemed = rate
+        .collateral_to_liquidity(collateral)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+    if redeemed < liquidity {
+        return Ok(collateral.checked_add(1).ok_or(ErrorCode::MathOverflow)?);
+    }
+    Ok(collateral)
+}
+
+/// USDC value of `collateral` cUSDC that can be paid out of it. One cUSDC unit is
+/// held back because `collateral_for_liquidity` rounds redemptions up by up to one.
+pub fn redeemable_liquidity(reserve: &Reserve, collateral: u64) -> Result<u64> {
+    collateral_to_liquidity(reserve, collateral.saturating_sub(1))
+}
+
+/// Spot deposit APY of the reserve in basis points.
+/// deposit_apy ~ utilization * borrow_rate * (1 - protocol_take_rate)
+pub fn deposit_apy_bps(reserve: &Reserve) -> Result<u64> {
+    // 1) Current annualized borrow rate, e.g. 0.10 for 10%
+    let current_borrow_rate = reserve
+        .current_borrow_rate()
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    // 2) utilization = borrowed_amount / (borrowed_amount + available_amount)
+    let borrowed_amount_wads = reserve.liquidity.borrowed_amount_wads;
+    let available_amount = Decimal::from(reserve.liquidity.available_amount);
+
+    let total_supply = borrowed_amount_wads
+        .try_add(available_amount)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    if total_supply == Decimal::zero() {
+        // No liquidity => APY is zero
+        return Ok(0);
+    }
+
+    let utilization = borrowed_amount_wads
+        .try_div(total_supply)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    // 3) Protocol take rate as a decimal
+    let protocol_take_rate = Decimal::from(reserve.config.protocol_take_rate as u64)
+        .try_div(Decimal::from(100u64))
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    // 4) deposit_apy = current_borrow_rate * utilization * (1 - protocol_take_rate)
+    let deposit_apy_decimal = Decimal::from(current_borrow_rate)
+        .try_mul(utilization)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?
+        .try_mul(Decimal::one().try_sub(protocol_take_rate)?)
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?;
+
+    // 5) WAD-scaled decimal to basis points (1.0 -> 10000 bps). Dividing by WAD
+    //    first would truncate every APY below 100% to zero.
+    let deposit_apy_bps = deposit_apy_decimal
+        .to_scaled_val()
+        .map_err(|_| error!(ErrorCode::InvalidReserve))?
+        .checked_div((WAD / 10_000).into())
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    deposit_apy_bps.try_into().map_err(|_| error!(ErrorCode::MathOverflow))
+}
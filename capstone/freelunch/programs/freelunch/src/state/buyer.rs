@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
 
 #[account]
 #[derive(InitSpace)]
@@ -8,4 +9,101 @@ pub struct BuyerAccount {
     pub unlockable_amount: u64, // Amount that can be withdrawn
     pub locked_amount: u64, // Locked amount for pending payments
     pub reward_amount: u64, // Rewards earned from staking
+    pub purchase_nonce: u64, // Next nonce a purchase intent must carry
+    pub account_number: u64, // Unique across accounts ever opened, so intents can't outlive the account they were signed for
+    pub open_pofs: u16, // PoFs not yet completed or cancelled
+    pub outstanding_amount: u64, // Still owed across open PoFs
+    pub lifetime_volume: u64, // Sum of every purchase ever made
+    pub max_exposure: u64, // Cap on outstanding_amount, set by the admin
+}
+
+impl BuyerAccount {
+    /// Moves `amount` of collateral from unlockable to locked.
+    pub fn lock_collateral(&mut self, amount: u64) -> Result<()> {
+        self.unlockable_amount = self.unlockable_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        self.locked_amount = self.locked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Moves `amount` of collateral from locked back to unlockable.
+    pub fn unlock_collateral(&mut self, amount: u64) -> Result<()> {
+        self.locked_amount = self.locked_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.unlockable_amount = self.unlockable_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Records a new PoF for `amount`, failing if it would take the buyer past `max_exposure`.
+    pub fn open_position(&mut self, amount: u64) -> Result<()> {
+        let outstanding = self.outstanding_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(outstanding <= self.max_exposure, ErrorCode::ExposureLimitExceeded);
+
+        self.outstanding_amount = outstanding;
+        self.open_pofs = self.open_pofs
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.lifetime_volume = self.lifetime_volume
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Drops `amount` paid or written off from the buyer's exposure, and the PoF
+    /// from the open count once it is `closed`.
+    pub fn settle_position(&mut self, amount: u64, closed: bool) -> Result<()> {
+        self.outstanding_amount = self.outstanding_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if closed {
+            self.open_pofs = self.open_pofs
+                .checked_sub(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buyer(max_exposure: u64) -> BuyerAccount {
+        BuyerAccount {
+            buyer: Pubkey::default(),
+            staked_amount: 0,
+            unlockable_amount: 0,
+            locked_amount: 0,
+            reward_amount: 0,
+            purchase_nonce: 0,
+            account_number: 0,
+            open_pofs: 0,
+            outstanding_amount: 0,
+            lifetime_volume: 0,
+            max_exposure,
+        }
+    }
+
+    #[test]
+    fn positions_respect_the_credit_limit() {
+        let mut b = buyer(100);
+        b.open_position(60).unwrap();
+        assert!(b.open_position(41).is_err());
+        b.open_position(40).unwrap();
+        assert_eq!((b.open_pofs, b.outstanding_amount, b.lifetime_volume), (2, 100, 100));
+
+        // Paying down frees room; volume is lifetime and never shrinks
+        b.settle_position(60, true).unwrap();
+        assert_eq!((b.open_pofs, b.outstanding_amount, b.lifetime_volume), (1, 40, 100));
+        b.open_position(60).unwrap();
+        assert_eq!(b.lifetime_volume, 160);
+    }
 }
@@ -0,0 +1,234 @@
+use anchor_lang::prelude::*;
+use crate::constants::{DISCOUNT_RATE_SCALE, FACTORING_DEAD_SHARES, SECONDS_PER_YEAR};
+use crate::error::ErrorCode;
+
+/// LP-funded pool that buys merchants' PoFs at a discount. Its value is the idle
+/// USDC in the liquidity account plus what factored PoFs still owe it, less the part
+/// of their discount not yet earned: each discount accrues evenly until its PoF is due.
+#[account]
+#[derive(InitSpace)]
+pub struct FactoringPool {
+    pub total_shares: u64, // LP shares outstanding
+    pub receivables: u64, // Face value still owed to the pool by factored PoFs
+    pub discount_bps: u64, // Annualized discount charged on the time left until a PoF is due
+    pub total_discount: u64, // Discount earned over the pool's lifetime
+    pub unearned_discount: u64, // Discount on factored PoFs not yet accrued into the pool's value
+    pub discount_rate: u128, // Discount accruing per second, scaled by DISCOUNT_RATE_SCALE
+    pub accrued_at: i64, // When unearned_discount was last accrued
+    pub bump: u8,
+    pub liquidity_bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LiquidityPosition {
+    pub lp: Pubkey,
+    pub shares: u64,
+}
+
+impl FactoringPool {
+    /// What the pool is worth: factored PoFs count at what was paid for them plus the
+    /// discount accrued so far. Call `accrue_discount` first.
+    pub fn value(&self, liquidity: u64) -> Result<u64> {
+        Ok(liquidity
+            .checked_add(self.receivables)
+            .and_then(|value| value.checked_sub(self.unearned_discount))
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    /// Moves the discount earned since `accrued_at` into the pool's value.
+    pub fn accrue_discount(&mut self, now: i64) -> Result<()> {
+        let elapsed = now.saturating_sub(self.accrued_at).max(0) as u128;
+        let earned = self.discount_rate
+            .checked_mul(elapsed)
+            .ok_or(ErrorCode::MathOverflow)?
+            / DISCOUNT_RATE_SCALE;
+        // Only a PoF left uncollected past its due date accrues for longer than it
+        // should, and dropping it hands the excess back
+        self.unearned_discount -= std::cmp::min(earned, self.unearned_discount as u128) as u64;
+        self.accrued_at = now;
+        Ok(())
+    }
+
+    /// Shares minted for depositing `amount` into a pool holding `liquidity` idle USDC.
+    /// The first deposit gives up `FACTORING_DEAD_SHARES` to the pool for good, so no
+    /// LP can hold every share and inflate their price by donating to the pool.
+    pub fn shares_for_deposit(&self, amount: u64, liquidity: u64) -> Result<u64> {
+        if self.total_shares == 0 {
+            return Ok(amount.saturating_sub(FACTORING_DEAD_SHARES));
+        }
+        let shares = (amount as u128)
+            .checked_mul(self.total_shares as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(self.value(liquidity)? as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(u64::try_from(shares).map_err(|_| error!(ErrorCode::MathOverflow))?)
+    }
+
+    /// Adds `shares` minted to an LP to the supply, plus the dead shares on the first deposit.
+    pub fn issue_shares(&mut self, shares: u64) -> Result<()> {
+        let dead = if self.total_shares == 0 { FACTORING_DEAD_SHARES } else { 0 };
+        self.total_shares = self.total_shares
+            .checked_add(shares)
+            .and_then(|total| total.checked_add(dead))
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// USDC that `shares` are currently worth, rounded down.
+    pub fn amount_for_shares(&self, shares: u64, liquidity: u64) -> Result<u64> {
+        let amount = (shares as u128)
+            .checked_mul(self.value(liquidity)? as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(self.total_shares as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))?)
+    }
+
+    /// Discount on `amount` paid `seconds_early`, never more than `amount` itself.
+    pub fn discount(&self, amount: u64, seconds_early: i64) -> Result<u64> {
+        let seconds_early = seconds_early.max(0) as u128;
+        let discount = (amount as u128)
+            .checked_mul(self.discount_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_mul(seconds_early)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000 * SECONDS_PER_YEAR as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(std::cmp::min(discount, amount as u128) as u64)
+    }
+
+    /// Per-second accrual, scaled by `DISCOUNT_RATE_SCALE`, of `discount` earned evenly
+    /// from `factored_at` until `due_at`.
+    pub fn discount_rate(discount: u64, factored_at: i64, due_at: i64) -> Result<u128> {
+        let term = due_at.saturating_sub(factored_at);
+        if term <= 0 {
+            return Ok(0);
+        }
+        Ok((discount as u128)
+            .checked_mul(DISCOUNT_RATE_SCALE)
+            .ok_or(ErrorCode::MathOverflow)?
+            / term as u128)
+    }
+
+    /// Records a PoF bought for `face_value - discount` at `now`. Its discount starts
+    /// accruing into the pool's value and is fully earned by `due_at`.
+    pub fn add_receivable(&mut self, face_value: u64, discount: u64, now: i64, due_at: i64) -> Result<()> {
+        self.receivables = self.receivables
+            .checked_add(face_value)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_discount = self.total_discount
+            .checked_add(discount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.unearned_discount = self.unearned_discount
+            .checked_add(discount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.discount_rate = self.discount_rate
+            .checked_add(Self::discount_rate(discount, now, due_at)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Stops accruing `discount` of a PoF factored at `factored_at` and due at `due_at`.
+    /// Whatever of it is still unearned at `now` is realized at once if the PoF paid off
+    /// early, or lost with the receivable if it was written off.
+    pub fn drop_discount(&mut self, discount: u64, factored_at: i64, due_at: i64, now: i64) -> Result<()> {
+        let rate = Self::discount_rate(discount, factored_at, due_at)?;
+        let accrued = rate
+            .checked_mul(now.saturating_sub(factored_at).max(0) as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / DISCOUNT_RATE_SCALE;
+        // The pool accrued `accrued` for this PoF, which is more than its discount if
+        // it stayed open past due; put that excess back before dropping the discount
+        let unearned = (self.unearned_discount as u128)
+            .checked_add(accrued)
+            .ok_or(ErrorCode::MathOverflow)?
+            .saturating_sub(discount as u128);
+        self.unearned_discount = u64::try_from(unearned).map_err(|_| error!(ErrorCode::MathOverflow))?;
+        // Saturating: a schedule that lost its last installment to a split dispute now
+        // ends earlier, so its rate recomputes slightly higher than what was added
+        self.discount_rate = self.discount_rate.saturating_sub(rate);
+        Ok(())
+    }
+
+    /// Drops `amount` collected or written off from what the pool is owed.
+    pub fn settle_receivable(&mut self, amount: u64) -> Result<()> {
+        self.receivables = self.receivables
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(total_shares: u64, receivables: u64) -> FactoringPool {
+        FactoringPool {
+            total_shares,
+            receivables,
+            discount_bps: 1000,
+            total_discount: 0,
+            unearned_discount: 0,
+            discount_rate: 0,
+            accrued_at: 0,
+            bump: 0,
+            liquidity_bump: 0,
+        }
+    }
+
+    #[test]
+    fn shares_track_liquidity_and_receivables() {
+        let mut empty = pool(0, 0);
+        assert_eq!(empty.shares_for_deposit(1_000, 0).unwrap(), 0);
+        assert_eq!(empty.shares_for_deposit(5_000, 0).unwrap(), 4_000);
+        empty.issue_shares(4_000).unwrap();
+        assert_eq!(empty.total_shares, 5_000);
+
+        // 1,000 shares backed by 600 idle + 600 owed: each share is worth 1.2
+        let pool = pool(1_000, 600);
+        assert_eq!(pool.amount_for_shares(1_000, 600).unwrap(), 1_200);
+        assert_eq!(pool.shares_for_deposit(1_200, 600).unwrap(), 1_000);
+        assert_eq!(pool.amount_for_shares(1, 600).unwrap(), 1);
+    }
+
+    #[test]
+    fn discount_scales_with_time_left() {
+        let pool = pool(0, 0);
+        assert_eq!(pool.discount(1_000, SECONDS_PER_YEAR).unwrap(), 100);
+        assert_eq!(pool.discount(1_000, SECONDS_PER_YEAR / 2).unwrap(), 50);
+        assert_eq!(pool.discount(1_000, -1).unwrap(), 0);
+        assert_eq!(pool.discount(1_000, 20 * SECONDS_PER_YEAR).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn receivables_are_valued_at_cost_plus_accrued_discount() {
+        // 900 paid for 1,000 due in 100 seconds; the other 100 accrues 1 a second
+        let mut pool = pool(1_000, 0);
+        pool.add_receivable(1_000, 100, 0, 100).unwrap();
+        assert_eq!(pool.value(100).unwrap(), 1_000);
+        pool.accrue_discount(40).unwrap();
+        assert_eq!(pool.value(100).unwrap(), 1_040);
+
+        // Paid off early: the rest of the discount is realized on collection
+        pool.settle_receivable(1_000).unwrap();
+        pool.drop_discount(100, 0, 100, 40).unwrap();
+        assert_eq!(pool.value(1_100).unwrap(), 1_100);
+        assert_eq!((pool.unearned_discount, pool.discount_rate), (0, 0));
+    }
+
+    #[test]
+    fn an_overdue_receivable_gives_back_what_it_over_accrued() {
+        let mut pool = pool(1_000, 0);
+        pool.add_receivable(1_000, 100, 0, 100).unwrap();
+        pool.add_receivable(1_000, 100, 0, 1_000).unwrap();
+
+        // At 150 the first PoF is overdue and has eaten 50 of the second's discount
+        pool.accrue_discount(150).unwrap();
+        assert_eq!(pool.unearned_discount, 200 - 150 - 15);
+        pool.drop_discount(100, 0, 100, 150).unwrap();
+        assert_eq!(pool.unearned_discount, 100 - 15);
+        assert_eq!(pool.discount_rate, FactoringPool::discount_rate(100, 0, 1_000).unwrap());
+    }
+}
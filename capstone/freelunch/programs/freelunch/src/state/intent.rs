@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use crate::constants::PURCHASE_INTENT_DOMAIN;
+use crate::state::Installment;
+
+/// The purchase terms a buyer signs off-chain. The admin submits it alongside an
+/// Ed25519 instruction so the buyer's consent is checked on-chain.
+///
+/// The signed message starts with `PURCHASE_INTENT_DOMAIN` and the program ID, so a
+/// signature is only good for this deployment and can't pass as any other message.
+/// It also names the buyer account it was signed for, so once that account is closed
+/// a reopened one, whose nonces start again at 0, won't accept it.
+pub struct PurchaseIntent {
+    pub buyer: Pubkey,
+    pub account_number: u64, // Must equal BuyerAccount.account_number
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub buffer_bps: u64,
+    pub nonce: u64,  // Must equal BuyerAccount.purchase_nonce
+    pub expiry: i64, // Unix timestamp after which the intent is void
+    pub installments: Vec<Installment>, // Payment plan the buyer agreed to
+}
+
+impl PurchaseIntent {
+    pub fn to_slice(&self) -> Vec<u8> {
+        let mut s = PURCHASE_INTENT_DOMAIN.to_vec();
+        s.extend_from_slice(&crate::ID.to_bytes());
+        s.extend_from_slice(&self.buyer.to_bytes());
+        s.extend_from_slice(&self.account_number.to_le_bytes());
+        s.extend_from_slice(&self.merchant.to_bytes());
+        s.extend_from_slice(&self.amount.to_le_bytes());
+        s.extend_from_slice(&self.buffer_bps.to_le_bytes());
+        s.extend_from_slice(&self.nonce.to_le_bytes());
+        s.extend_from_slice(&self.expiry.to_le_bytes());
+        for installment in &self.installments {
+            s.extend_from_slice(&installment.due_at.to_le_bytes());
+            s.extend_from_slice(&installment.amount.to_le_bytes());
+        }
+        s
+    }
+}
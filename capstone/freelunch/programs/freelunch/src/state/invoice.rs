@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+/// A merchant-issued payment request. Any staked buyer can accept it before
+/// `expires_at`, which opens a PoF numbered `invoice_number` and closes the invoice.
+#[account]
+#[derive(InitSpace)]
+pub struct Invoice {
+    pub merchant: Pubkey, // The merchant requesting payment
+    pub invoice_number: u64, // Taken from MerchantAccount.payment_number, reused by the PoF
+    pub amount: u64, // USDC requested
+    pub memo_hash: [u8; 32], // Hash of the off-chain order details
+    pub created_at: i64,
+    pub expires_at: i64, // Unix timestamp after which the invoice can no longer be accepted
+}
+
+impl Invoice {
+    pub fn is_expired(&self, now: i64) -> bool {
+        now > self.expires_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expires_strictly_after_expires_at() {
+        let invoice = Invoice {
+            merchant: Pubkey::default(),
+            invoice_number: 0,
+            amount: 100_000_000,
+            memo_hash: [0; 32],
+            created_at: 1_000,
+            expires_at: 2_000,
+        };
+
+        assert!(!invoice.is_expired(1_000));
+        assert!(!invoice.is_expired(2_000));
+        assert!(invoice.is_expired(2_001));
+    }
+}
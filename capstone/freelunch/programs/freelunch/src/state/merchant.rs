@@ -8,6 +8,8 @@ pub struct MerchantAccount {
     pub payment_number: u64, // Number of payments received
     pub amount_transacted: u64, // Total amount of USDC received from buyers
     pub seed:u128,
+    pub settlement_mint: Option<Pubkey>, // Token the merchant wants instead of USDC (None: USDC)
+    pub settlement_pool: Option<Pubkey>, // AMM pool config used to swap USDC into settlement_mint
 
 
 }
\ No newline at end of file
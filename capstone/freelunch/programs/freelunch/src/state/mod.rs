@@ -1,9 +1,17 @@
 pub mod buyer;
+pub mod factoring;
+pub mod intent;
+pub mod invoice;
 pub mod merchant;
 pub mod payment;
+pub mod status;
 pub mod vault;
 
 pub use buyer::*;
+pub use factoring::*;
+pub use intent::*;
+pub use invoice::*;
 pub use merchant::*;
 pub use payment::*;
+pub use status::*;
 pub use vault::*;
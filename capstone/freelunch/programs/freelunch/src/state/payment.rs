@@ -1,14 +1,493 @@
 use anchor_lang::prelude::*;
+use crate::constants::{MAX_INSTALLMENTS, SECONDS_PER_YEAR, YIELD_INDEX_SCALE};
+use crate::error::ErrorCode;
+use crate::state::PaymentStatus;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub struct Installment {
+    pub due_at: i64, // Unix timestamp the installment should be paid by
+    pub amount: u64, // Portion of payment_amount due at that time
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct ProofOfFuturePayment {
     pub payment_amount: u64, // Amount owed to merchant
-    pub locked_collateral: u64,   // How much is locked to generate yield for payment
-    pub admin: Pubkey, // Protocol admin managing payouts
+    pub locked_collateral: u64,   // How much is still locked to generate yield for payment
+    pub admin: Pubkey, // Paid for the PoF account (the relaying admin, or the buyer for invoices)
     pub buyer: Pubkey, // The buyer responsible for the payment
     pub merchant: Pubkey, // The merchant receiving the payment
-    pub completed: u8, // Payment status (0: Pending, 1: Completed)
+    pub status: PaymentStatus, // Payment lifecycle state
     pub payment_number: u64, // Payment ID for tracking
     pub amount_fulfilled: u64, // Amount already paid
+    pub created_at: i64, // Unix timestamp of the purchase
+    #[max_len(MAX_INSTALLMENTS)]
+    pub installments: Vec<Installment>, // Optional schedule; empty means one lump sum
+    pub current_installment: u8, // Index of the first installment not yet cleared
+    pub earned_unclaimed: u64, // Yield credited to this PoF but not yet paid to the merchant
+    pub factored: bool, // Sold to the factoring pool, which now collects the earned yield
+    pub factored_at: i64, // When it was factored; its discount accrues to the pool from then until due_at
+    pub factoring_discount: u64, // Discount the pool still accrues on it
+    pub yield_index: u128, // Vault yield_index this PoF's allotment was last brought up to
+    pub allotted_yield: u64, // Pool yield allotted to this PoF and not yet credited
+}
+
+impl ProofOfFuturePayment {
+    pub fn remaining_due(&self) -> Result<u64> {
+        Ok(self.payment_amount
+            .checked_sub(self.amount_fulfilled)
+            .ok_or(ErrorCode::MathOverflow)?)
+    }
+
+    /// Fails unless funds may currently move for this PoF.
+    pub fn require_payable(&self) -> Result<()> {
+        match self.status {
+            PaymentStatus::Completed => err!(ErrorCode::PaymentAlreadyCompleted),
+            PaymentStatus::Disputed => err!(ErrorCode::PaymentDisputed),
+            status if !status.is_payable() => err!(ErrorCode::InvalidStatusTransition),
+            _ => Ok(()),
+        }
+    }
+
+    /// The status implied by `amount_fulfilled` alone.
+    pub fn progress_status(&self) -> PaymentStatus {
+        if self.amount_fulfilled >= self.payment_amount {
+            PaymentStatus::Completed
+        } else if self.amount_fulfilled > 0 {
+            PaymentStatus::PartiallyPaid
+        } else {
+            PaymentStatus::Pending
+        }
+    }
+
+    /// An installment plan must be ordered by due date and add up to `payment_amount`.
+    pub fn validate_schedule(installments: &[Installment], payment_amount: u64) -> Result<()> {
+        if installments.is_empty() {
+            return Ok(());
+        }
+        require!(installments.len() <= MAX_INSTALLMENTS, ErrorCode::InvalidInstallmentSchedule);
+
+        let mut total: u64 = 0;
+        let mut last_due_at = i64::MIN;
+        for installment in installments {
+            require!(installment.amount > 0, ErrorCode::InvalidInstallmentSchedule);
+            require!(installment.due_at > last_due_at, ErrorCode::InvalidInstallmentSchedule);
+            last_due_at = installment.due_at;
+            total = total
+                .checked_add(installment.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        require_eq!(total, payment_amount, ErrorCode::InvalidInstallmentSchedule);
+        Ok(())
+    }
+
+    /// The next installment still to be cleared, if any.
+    pub fn current(&self) -> Option<&Installment> {
+        self.installments.get(self.current_installment as usize)
+    }
+
+    /// Advances past every installment now covered by `amount_fulfilled` and returns
+    /// the collateral freed by them, each releasing its share of what is still locked.
+    /// A completed PoF frees all remaining collateral.
+    pub fn clear_installments(&mut self) -> Result<u64> {
+        if self.status == PaymentStatus::Completed {
+            self.current_installment = self.installments.len() as u8;
+            return Ok(std::mem::take(&mut self.locked_collateral));
+        }
+
+        let mut cleared: u64 = 0;
+        for installment in &self.installments[..self.current_installment as usize] {
+            cleared = cleared
+                .checked_add(installment.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        let mut scheduled_left = self.payment_amount.saturating_sub(cleared);
+
+        let mut released: u64 = 0;
+        while let Some(installment) = self.current().copied() {
+            let next_cleared = cleared
+                .checked_add(installment.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            if next_cleared > self.amount_fulfilled || scheduled_left == 0 {
+                break;
+            }
+
+            let share = (self.locked_collateral as u128)
+                .checked_mul(installment.amount as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(scheduled_left as u128)
+                .ok_or(ErrorCode::MathOverflow)? as u64;
+            let share = std::cmp::min(share, self.locked_collateral);
+
+            self.locked_collateral -= share;
+            released = released
+                .checked_add(share)
+                .ok_or(ErrorCode::MathOverflow)?;
+            scheduled_left = scheduled_left.saturating_sub(installment.amount);
+            cleared = next_cleared;
+            self.current_installment += 1;
+        }
+
+        Ok(released)
+    }
+
+    /// Writes `amount` off what is still due. Installments not yet cleared shrink in
+    /// proportion, and any that round down to nothing are dropped, so the plan still
+    /// adds up to `payment_amount`.
+    pub fn forgive(&mut self, amount: u64) -> Result<()> {
+        require!(amount <= self.remaining_due()?, ErrorCode::MathOverflow);
+
+        let current = self.current_installment as usize;
+        let mut cleared: u64 = 0;
+        for installment in &self.installments[..current] {
+            cleared = cleared
+                .checked_add(installment.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        let scheduled_left = self.payment_amount
+            .checked_sub(cleared)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.payment_amount -= amount;
+        let new_left = self.payment_amount
+            .checked_sub(cleared)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let pending = &mut self.installments[current..];
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let mut assigned: u64 = 0;
+        for installment in pending.iter_mut() {
+            installment.amount = ((installment.amount as u128)
+                .checked_mul(new_left as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                / scheduled_left as u128) as u64;
+            assigned += installment.amount;
+        }
+        // Rounding dust goes to the last installment
+        if let Some(last) = pending.last_mut() {
+            last.amount += new_left - assigned;
+        }
+        self.installments.retain(|installment| installment.amount > 0);
+        Ok(())
+    }
+
+    /// Records up to `amount` towards the payment and advances the status.
+    /// Returns the amount actually applied (capped at what is still due).
+    pub fn apply_payment(&mut self, amount: u64) -> Result<u64> {
+        self.require_payable()?;
+
+        let pay_now = std::cmp::min(amount, self.remaining_due()?);
+
+        self.amount_fulfilled = self.amount_fulfilled
+            .checked_add(pay_now)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let next = self.progress_status();
+        if next != self.status {
+            self.status.transition_to(next)?;
+        }
+
+        Ok(pay_now)
+    }
+
+    /// Credits up to `amount` of redeemed yield to this PoF. Only the harvest and
+    /// fulfillment paths call this; it is what later claims are capped at.
+    pub fn credit_earned(&mut self, amount: u64) -> Result<u64> {
+        let credited = self.apply_payment(amount)?;
+        self.earned_unclaimed = self.earned_unclaimed
+            .checked_add(credited)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(credited)
+    }
+
+    /// Adds this PoF's share of the yield allotted since its last update. Must run
+    /// before `locked_collateral` changes, so yield goes to the lock that earned it.
+    pub fn accrue_yield(&mut self, yield_index: u128) -> Result<()> {
+        let share = (self.locked_collateral as u128)
+            .checked_mul(yield_index.checked_sub(self.yield_index).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::MathOverflow)?
+            / YIELD_INDEX_SCALE;
+        self.allotted_yield = self.allotted_yield
+            .checked_add(u64::try_from(share).map_err(|_| error!(ErrorCode::MathOverflow))?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.yield_index = yield_index;
+        Ok(())
+    }
+
+    /// Takes up to `amount` of the allotment to credit this PoF.
+    pub fn take_allotted(&mut self, amount: u64) -> u64 {
+        let taken = std::cmp::min(amount, self.allotted_yield);
+        self.allotted_yield -= taken;
+        taken
+    }
+
+    /// When the payment is due in full: with its last installment, or for a lump sum
+    /// a year after purchase, the horizon its collateral was sized for.
+    pub fn due_at(&self) -> i64 {
+        self.installments
+            .last()
+            .map_or(self.created_at.saturating_add(SECONDS_PER_YEAR), |installment| installment.due_at)
+    }
+
+    /// When the locked collateral will have earned what is still due at `apy_bps`.
+    pub fn projected_payoff_at(&self, now: i64, apy_bps: u64) -> Result<Option<i64>> {
+        projected_payoff_at(now, self.remaining_due()?, self.locked_collateral, apy_bps)
+    }
+
+    /// Collateral that would earn what is still due by `due_at` at `apy_bps`, rounded
+    /// up. None once the due date has passed, when no amount is enough.
+    pub fn required_collateral(&self, now: i64, apy_bps: u64) -> Result<Option<u64>> {
+        let seconds_left = self.due_at().saturating_sub(now);
+        if seconds_left <= 0 {
+            return Ok(None);
+        }
+
+        let owed = (self.remaining_due()? as u128)
+            .checked_mul(10000 * SECONDS_PER_YEAR as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let yield_per_unit = (apy_bps as u128)
+            .checked_mul(seconds_left as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(yield_per_unit > 0, ErrorCode::MathOverflow);
+
+        let required = owed.div_ceil(yield_per_unit);
+        Ok(Some(u64::try_from(required).map_err(|_| error!(ErrorCode::MathOverflow))?))
+    }
+
+    /// Adds `amount` of the buyer's collateral to this PoF's lock.
+    pub fn top_up(&mut self, amount: u64) -> Result<()> {
+        self.require_payable()?;
+        self.locked_collateral = self.locked_collateral
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Takes up to `amount` of the earned balance for payout to the merchant.
+    pub fn take_earned(&mut self, amount: u64) -> Result<u64> {
+        require!(self.status != PaymentStatus::Disputed, ErrorCode::PaymentDisputed);
+
+        let taken = std::cmp::min(amount, self.earned_unclaimed);
+        require!(taken > 0, ErrorCode::NothingToClaim);
+
+        self.earned_unclaimed -= taken;
+        Ok(taken)
+    }
+}
+
+/// `owed / (locked * apy)` years after `now`; None if `locked` earns nothing.
+pub fn projected_payoff_at(now: i64, owed: u64, locked: u64, apy_bps: u64) -> Result<Option<i64>> {
+    if owed == 0 {
+        return Ok(Some(now));
+    }
+
+    let yearly_yield = (locked as u128)
+        .checked_mul(apy_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if yearly_yield == 0 {
+        return Ok(None);
+    }
+
+    let seconds = (owed as u128)
+        .checked_mul(10000 * SECONDS_PER_YEAR as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(yearly_yield)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let seconds = i64::try_from(seconds).map_err(|_| error!(ErrorCode::MathOverflow))?;
+
+    Ok(now.checked_add(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pof(payment_amount: u64, locked_collateral: u64, installments: Vec<Installment>) -> ProofOfFuturePayment {
+        ProofOfFuturePayment {
+            payment_amount,
+            locked_collateral,
+            admin: Pubkey::default(),
+            buyer: Pubkey::default(),
+            merchant: Pubkey::default(),
+            status: PaymentStatus::Pending,
+            payment_number: 0,
+            amount_fulfilled: 0,
+            created_at: 0,
+            installments,
+            current_installment: 0,
+            earned_unclaimed: 0,
+            factored: false,
+            factored_at: 0,
+            factoring_discount: 0,
+            yield_index: 0,
+            allotted_yield: 0,
+        }
+    }
+
+    fn plan(amounts: &[u64]) -> Vec<Installment> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| Installment { due_at: (i as i64 + 1) * 86_400, amount: *amount })
+            .collect()
+    }
+
+    #[test]
+    fn schedule_must_sum_to_payment_and_be_ordered() {
+        assert!(ProofOfFuturePayment::validate_schedule(&[], 100).is_ok());
+        assert!(ProofOfFuturePayment::validate_schedule(&plan(&[40, 60]), 100).is_ok());
+        assert!(ProofOfFuturePayment::validate_schedule(&plan(&[40, 50]), 100).is_err());
+        assert!(ProofOfFuturePayment::validate_schedule(&plan(&[100, 0]), 100).is_err());
+
+        let mut unordered = plan(&[40, 60]);
+        unordered.swap(0, 1);
+        assert!(ProofOfFuturePayment::validate_schedule(&unordered, 100).is_err());
+
+        assert!(ProofOfFuturePayment::validate_schedule(&plan(&[1; MAX_INSTALLMENTS + 1]), 13).is_err());
+    }
+
+    #[test]
+    fn collateral_is_released_as_installments_clear() {
+        let mut proof = pof(100, 1000, plan(&[25, 25, 50]));
+
+        // Partway into the first installment nothing is freed
+        proof.apply_payment(20).unwrap();
+        assert_eq!(proof.clear_installments().unwrap(), 0);
+        assert_eq!(proof.current_installment, 0);
+
+        // Clearing the first two releases half the collateral
+        proof.apply_payment(30).unwrap();
+        assert_eq!(proof.clear_installments().unwrap(), 500);
+        assert_eq!(proof.current_installment, 2);
+        assert_eq!(proof.locked_collateral, 500);
+        assert_eq!(proof.current().unwrap().amount, 50);
+
+        // Completion frees the rest
+        proof.apply_payment(50).unwrap();
+        assert_eq!(proof.status, PaymentStatus::Completed);
+        assert_eq!(proof.clear_installments().unwrap(), 500);
+        assert_eq!(proof.locked_collateral, 0);
+        assert!(proof.current().is_none());
+    }
+
+    #[test]
+    fn forgiving_rescales_the_installments_still_due() {
+        let mut proof = pof(100, 1000, plan(&[25, 25, 50]));
+        proof.apply_payment(30).unwrap();
+        proof.clear_installments().unwrap();
+        assert_eq!(proof.current_installment, 1);
+
+        // Half of the 70 still due is written off; the 75 left on the plan becomes 40
+        proof.forgive(35).unwrap();
+        assert_eq!(proof.payment_amount, 65);
+        let amounts: Vec<u64> = proof.installments.iter().map(|i| i.amount).collect();
+        assert_eq!(amounts, vec![25, 13, 27]);
+        assert!(ProofOfFuturePayment::validate_schedule(&proof.installments, proof.payment_amount).is_ok());
+
+        proof.apply_payment(35).unwrap();
+        assert_eq!(proof.status, PaymentStatus::Completed);
+
+        // Installments that round down to nothing are dropped
+        let mut proof = pof(100, 1000, plan(&[1, 99]));
+        proof.forgive(99).unwrap();
+        assert_eq!(proof.installments, vec![Installment { due_at: 2 * 86_400, amount: 1 }]);
+
+        // Forgiving everything left leaves only what was already cleared
+        let mut proof = pof(100, 1000, plan(&[40, 60]));
+        proof.apply_payment(40).unwrap();
+        proof.clear_installments().unwrap();
+        proof.forgive(60).unwrap();
+        assert_eq!(proof.installments.len(), 1);
+        assert!(ProofOfFuturePayment::validate_schedule(&proof.installments, proof.payment_amount).is_ok());
+        assert!(proof.forgive(1).is_err());
+    }
+
+    #[test]
+    fn lump_sum_releases_only_on_completion() {
+        let mut proof = pof(100, 1000, vec![]);
+
+        proof.apply_payment(99).unwrap();
+        assert_eq!(proof.clear_installments().unwrap(), 0);
+
+        proof.apply_payment(1).unwrap();
+        assert_eq!(proof.clear_installments().unwrap(), 1000);
+    }
+
+    #[test]
+    fn claims_are_capped_at_credited_yield() {
+        let mut proof = pof(100, 1_000, vec![]);
+        assert!(proof.take_earned(10).is_err());
+
+        assert_eq!(proof.credit_earned(30).unwrap(), 30);
+        assert_eq!(proof.take_earned(50).unwrap(), 30);
+        assert!(proof.take_earned(1).is_err());
+
+        // Crediting never exceeds what is still due, and earnings stay claimable once completed
+        assert_eq!(proof.credit_earned(500).unwrap(), 70);
+        assert_eq!(proof.status, PaymentStatus::Completed);
+        assert_eq!(proof.take_earned(70).unwrap(), 70);
+    }
+
+    #[test]
+    fn disputed_earnings_are_frozen() {
+        let mut proof = pof(100, 1_000, vec![]);
+        proof.credit_earned(40).unwrap();
+        proof.status.transition_to(PaymentStatus::Disputed).unwrap();
+        assert!(proof.take_earned(40).is_err());
+        assert_eq!(proof.earned_unclaimed, 40);
+    }
+
+    #[test]
+    fn collateral_requirement_tracks_apy_and_time_left() {
+        const YEAR: i64 = SECONDS_PER_YEAR;
+        let proof = pof(100, 1_000, vec![]);
+        assert_eq!(proof.due_at(), YEAR);
+
+        // 1,000 at 10% earns the 100 in exactly the year the lump sum was sized for
+        assert_eq!(proof.projected_payoff_at(0, 1_000).unwrap(), Some(YEAR));
+        assert_eq!(proof.required_collateral(0, 1_000).unwrap(), Some(1_000));
+
+        // Halving the APY doubles both the time and the collateral needed
+        assert_eq!(proof.projected_payoff_at(0, 500).unwrap(), Some(2 * YEAR));
+        assert_eq!(proof.required_collateral(0, 500).unwrap(), Some(2_000));
+
+        // Half the time left needs twice the collateral; none is enough once overdue
+        assert_eq!(proof.required_collateral(YEAR / 2, 1_000).unwrap(), Some(2_000));
+        assert_eq!(proof.required_collateral(YEAR, 1_000).unwrap(), None);
+
+        let planned = pof(100, 1_000, plan(&[40, 60]));
+        assert_eq!(planned.due_at(), 2 * 86_400);
+    }
+
+    #[test]
+    fn allotments_follow_the_lock_that_earned_them() {
+        let mut proof = pof(100, 1_000, vec![]);
+
+        // 0.025 per unit locked
+        proof.accrue_yield(YIELD_INDEX_SCALE / 40).unwrap();
+        assert_eq!(proof.allotted_yield, 25);
+
+        // A top-up only earns from the next allotment on
+        proof.top_up(1_000).unwrap();
+        proof.accrue_yield(YIELD_INDEX_SCALE / 40).unwrap();
+        assert_eq!(proof.allotted_yield, 25);
+        proof.accrue_yield(YIELD_INDEX_SCALE * 3 / 80).unwrap();
+        assert_eq!(proof.allotted_yield, 50);
+
+        assert_eq!(proof.take_allotted(30), 30);
+        assert_eq!(proof.take_allotted(30), 20);
+        assert!(proof.accrue_yield(0).is_err());
+    }
+
+    #[test]
+    fn top_up_only_while_payable() {
+        let mut proof = pof(100, 1_000, vec![]);
+        proof.top_up(250).unwrap();
+        assert_eq!(proof.locked_collateral, 1_250);
+
+        proof.apply_payment(100).unwrap();
+        assert!(proof.top_up(1).is_err());
+    }
 }
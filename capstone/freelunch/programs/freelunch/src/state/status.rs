@@ -0,0 +1,105 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+/// Lifecycle of a ProofOfFuturePayment.
+///
+/// A PoF is created Pending, and every instruction that touches it afterwards
+/// moves it through `transition_to`, so the set of legal moves lives in exactly
+/// one place.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, InitSpace)]
+pub enum PaymentStatus {
+    Pending,       // Created, nothing paid yet
+    PartiallyPaid, // Some of the payment_amount has reached the merchant
+    Completed,     // Fully paid, collateral released
+    Cancelled,     // Backed out before completion, collateral released
+    Defaulted,     // Collateral could not cover the payment
+    Disputed,      // Frozen until the dispute is resolved
+}
+
+impl PaymentStatus {
+    /// Completed, Cancelled and Defaulted PoFs never change again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Cancelled | Self::Defaulted)
+    }
+
+    /// Whether funds may currently flow towards the merchant.
+    pub fn is_payable(&self) -> bool {
+        matches!(self, Self::Pending | Self::PartiallyPaid)
+    }
+
+    pub fn can_transition_to(&self, next: PaymentStatus) -> bool {
+        use PaymentStatus::*;
+        matches!(
+            (self, next),
+            (Pending, PartiallyPaid | Completed | Cancelled | Defaulted | Disputed)
+                | (PartiallyPaid, PartiallyPaid | Completed | Cancelled | Defaulted | Disputed)
+                | (Disputed, Pending | PartiallyPaid | Completed | Cancelled)
+        )
+    }
+
+    pub fn transition_to(&mut self, next: PaymentStatus) -> Result<()> {
+        if self.can_transition_to(next) {
+            *self = next;
+            return Ok(());
+        }
+        match self {
+            Self::Completed => err!(ErrorCode::PaymentAlreadyCompleted),
+            _ => err!(ErrorCode::InvalidStatusTransition),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaymentStatus::{self, *};
+
+    const ALL: [PaymentStatus; 6] = [Pending, PartiallyPaid, Completed, Cancelled, Defaulted, Disputed];
+
+    const LEGAL: [(PaymentStatus, PaymentStatus); 14] = [
+        (Pending, PartiallyPaid),
+        (Pending, Completed),
+        (Pending, Cancelled),
+        (Pending, Defaulted),
+        (Pending, Disputed),
+        (PartiallyPaid, PartiallyPaid),
+        (PartiallyPaid, Completed),
+        (PartiallyPaid, Cancelled),
+        (PartiallyPaid, Defaulted),
+        (PartiallyPaid, Disputed),
+        (Disputed, Pending),
+        (Disputed, PartiallyPaid),
+        (Disputed, Completed),
+        (Disputed, Cancelled),
+    ];
+
+    #[test]
+    fn legal_transitions_apply() {
+        for (from, to) in LEGAL {
+            let mut status = from;
+            assert!(status.transition_to(to).is_ok(), "{:?} -> {:?} should be legal", from, to);
+            assert_eq!(status, to);
+        }
+    }
+
+    #[test]
+    fn illegal_transitions_are_rejected_and_leave_status_untouched() {
+        for from in ALL {
+            for to in ALL {
+                if LEGAL.contains(&(from, to)) {
+                    continue;
+                }
+                let mut status = from;
+                assert!(status.transition_to(to).is_err(), "{:?} -> {:?} should be illegal", from, to);
+                assert_eq!(status, from);
+            }
+        }
+    }
+
+    #[test]
+    fn terminal_states_have_no_exits() {
+        for from in ALL.into_iter().filter(|s| s.is_terminal()) {
+            assert!(!from.is_payable());
+            assert!(ALL.iter().all(|to| !from.can_transition_to(*to)));
+        }
+    }
+}
@@ -1,11 +1,310 @@
 use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use crate::constants::{APY_EMA_ALPHA_BPS, APY_REFRESH_INTERVAL, YIELD_INDEX_SCALE};
+use crate::state::ProofOfFuturePayment;
 
 #[account]
 #[derive(InitSpace)]
 pub struct ProtocolVault {
     pub admin: Pubkey, // Admin of the protocol
+    pub pending_admin: Option<Pubkey>, // Proposed admin, takes over once it accepts
+    pub paused: bool, // Emergency stop for stake, purchases and payouts
+    pub arbiter: Pubkey, // Resolves disputes between buyers and merchants
+    pub usdc_mint: Pubkey, // Liquidity mint of the pinned reserve
+    pub solend_program: Pubkey, // Lending program the pinned reserve belongs to
+    pub solend_reserve: Pubkey, // The only reserve stake and redemptions go through
+    pub reserve_liquidity_supply: Pubkey, // Pinned reserve's liquidity supply
+    pub reserve_collateral_mint: Pubkey, // Pinned reserve's collateral mint (cUSDC)
+    pub lending_market: Pubkey, // Pinned reserve's lending market
+    pub usdc_account: Pubkey, // Vault-owned USDC account redemptions pass through
+    pub collateral_account: Pubkey, // Vault-owned cUSDC account holding all stakers' collateral
     pub total_staked: u64, // Total USDC staked across all users
+    pub total_locked: u64, // Staked USDC locked as PoF collateral
     pub total_rewards: u64, // Total rewards generated from staking
+    pub yield_index: u128, // Yield allotted per unit of locked collateral, scaled by YIELD_INDEX_SCALE
+    pub allotted_yield: u64, // Yield allotted through yield_index that no PoF has been credited yet
     pub pending_payments: u64, // Total outstanding Proof of Future Payments
+    pub buyer_accounts_opened: u64, // Buyer accounts ever opened; numbers the next one
+    pub keeper_fee_bps: u16, // Paid to whoever cranks harvest_and_pay
+    pub protocol_fee_bps: u16, // Skimmed from yield into the treasury
+    pub total_protocol_fees: u64, // Lifetime fees sent to the treasury
+    pub treasury_bump: u8,
+    pub min_buffer_bps: u64, // Smallest collateral buffer a PoF may be created with
+    pub max_purchase_amount: u64, // Largest payment_amount a single PoF may have
+    pub apy_floor_bps: u64, // PoFs are refused when the APY falls below this
+    pub apy_cap_bps: u64, // APY used for collateral never exceeds this
+    pub use_smoothed_apy: bool, // Also bound the APY by the on-chain EMA
+    pub smoothed_apy_bps: u64, // EMA of the deposit APY, updated by refresh_apy
+    pub apy_updated_at: i64, // Last time smoothed_apy_bps was updated
     pub bump: u8,
 }
+
+// Every instruction that moves principal, collateral, liabilities or yield
+// updates the global totals through these helpers.
+impl ProtocolVault {
+    pub fn record_stake(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self.total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_unstake(&mut self, amount: u64) -> Result<()> {
+        self.total_staked = self.total_staked
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientFunds)?;
+        Ok(())
+    }
+
+    pub fn lock_collateral(&mut self, amount: u64) -> Result<()> {
+        self.total_locked = self.total_locked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn release_collateral(&mut self, amount: u64) -> Result<()> {
+        self.total_locked = self.total_locked
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn add_liability(&mut self, amount: u64) -> Result<()> {
+        self.pending_payments = self.pending_payments
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn settle_liability(&mut self, amount: u64) -> Result<()> {
+        self.pending_payments = self.pending_payments
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_yield(&mut self, amount: u64) -> Result<()> {
+        self.total_rewards = self.total_rewards
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_protocol_fee(&mut self, amount: u64) -> Result<()> {
+        self.total_protocol_fees = self.total_protocol_fees
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Keeper fee owed on `amount` of yield credited to PoFs.
+    pub fn keeper_fee(&self, amount: u64) -> Result<u64> {
+        bps_of(amount, self.keeper_fee_bps)
+    }
+
+    /// Treasury's cut of `amount` of yield credited to PoFs.
+    pub fn protocol_fee(&self, amount: u64) -> Result<u64> {
+        bps_of(amount, self.protocol_fee_bps)
+    }
+
+    /// Yield above staked principal that can be paid out when `fee_bps` of fees go
+    /// on top of it, so payouts plus fees never dip into principal.
+    pub fn payable_yield(&self, collateral_value: u64, fee_bps: u64) -> Result<u64> {
+        let accrued = collateral_value.saturating_sub(self.total_staked);
+        Ok((accrued as u128)
+            .checked_mul(10000)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000 + fee_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64)
+    }
+
+    /// Allots yield accrued since the last call to every open PoF, pro rata to its
+    /// locked collateral, by raising `yield_index`. `payable_yield` is all yield that
+    /// could be paid out now, so only what isn't allotted yet is new. While nothing is
+    /// locked the yield waits for the next call.
+    pub fn allot_yield(&mut self, payable_yield: u64) -> Result<()> {
+        let new_yield = payable_yield.saturating_sub(self.allotted_yield);
+        if new_yield == 0 || self.total_locked == 0 {
+            return Ok(());
+        }
+
+        let per_locked = (new_yield as u128)
+            .checked_mul(YIELD_INDEX_SCALE)
+            .ok_or(ErrorCode::MathOverflow)?
+            / self.total_locked as u128;
+        self.yield_index = self.yield_index
+            .checked_add(per_locked)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.allotted_yield = payable_yield;
+        Ok(())
+    }
+
+    /// Takes `amount` off the allotted total once a PoF has used it, or has given it
+    /// back for the next `allot_yield` to share out again.
+    pub fn unallot_yield(&mut self, amount: u64) -> Result<()> {
+        self.allotted_yield = self.allotted_yield
+            .checked_sub(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Gives back everything `proof` was allotted once it stops taking payments.
+    pub fn reclaim_allotment(&mut self, proof: &mut ProofOfFuturePayment) -> Result<()> {
+        proof.accrue_yield(self.yield_index)?;
+        let unused = std::mem::take(&mut proof.allotted_yield);
+        self.unallot_yield(unused)
+    }
+
+    /// APY used to size PoF collateral. Takes the lower of the spot and smoothed
+    /// APY and caps it, so a short spike can't shrink the collateral requirement.
+    pub fn conservative_apy_bps(&self, spot_apy_bps: u64) -> Result<u64> {
+        let mut apy_bps = spot_apy_bps.min(self.apy_cap_bps);
+        if self.use_smoothed_apy {
+            apy_bps = apy_bps.min(self.smoothed_apy_bps);
+        }
+        require!(apy_bps > 0 && apy_bps >= self.apy_floor_bps, ErrorCode::InvalidAPY);
+        Ok(apy_bps)
+    }
+
+    /// Folds a new spot APY into the EMA. Spot is capped first and updates are
+    /// rate limited so repeated cranks during a spike can't drag the average up.
+    pub fn update_smoothed_apy(&mut self, spot_apy_bps: u64, now: i64) -> Result<()> {
+        let spot = spot_apy_bps.min(self.apy_cap_bps);
+
+        if self.apy_updated_at == 0 {
+            self.smoothed_apy_bps = spot;
+        } else {
+            require!(
+                now >= self.apy_updated_at.saturating_add(APY_REFRESH_INTERVAL),
+                ErrorCode::ApyRefreshTooSoon
+            );
+            // ema = alpha * spot + (1 - alpha) * ema
+            self.smoothed_apy_bps = ((spot as u128)
+                .checked_mul(APY_EMA_ALPHA_BPS as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_add(
+                    (self.smoothed_apy_bps as u128)
+                        .checked_mul((10000 - APY_EMA_ALPHA_BPS) as u128)
+                        .ok_or(ErrorCode::MathOverflow)?
+                )
+                .ok_or(ErrorCode::MathOverflow)?
+                / 10000) as u64;
+        }
+        self.apy_updated_at = now;
+        Ok(())
+    }
+}
+
+fn bps_of(amount: u64, bps: u16) -> Result<u64> {
+    Ok((amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::MathOverflow)? as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(apy_floor_bps: u64, apy_cap_bps: u64, use_smoothed_apy: bool, smoothed_apy_bps: u64) -> ProtocolVault {
+        ProtocolVault {
+            admin: Pubkey::default(),
+            pending_admin: None,
+            paused: false,
+            arbiter: Pubkey::default(),
+            usdc_mint: Pubkey::default(),
+            solend_program: Pubkey::default(),
+            solend_reserve: Pubkey::default(),
+            reserve_liquidity_supply: Pubkey::default(),
+            reserve_collateral_mint: Pubkey::default(),
+            lending_market: Pubkey::default(),
+            usdc_account: Pubkey::default(),
+            collateral_account: Pubkey::default(),
+            total_staked: 0,
+            total_locked: 0,
+            total_rewards: 0,
+            yield_index: 0,
+            allotted_yield: 0,
+            pending_payments: 0,
+            buyer_accounts_opened: 0,
+            keeper_fee_bps: 0,
+            protocol_fee_bps: 0,
+            total_protocol_fees: 0,
+            treasury_bump: 0,
+            min_buffer_bps: 0,
+            max_purchase_amount: 0,
+            apy_floor_bps,
+            apy_cap_bps,
+            use_smoothed_apy,
+            smoothed_apy_bps,
+            apy_updated_at: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn payable_yield_leaves_room_for_fees() {
+        let mut v = vault(50, 2000, false, 0);
+        v.total_staked = 1_000;
+        assert_eq!(v.payable_yield(900, 0).unwrap(), 0);
+        assert_eq!(v.payable_yield(1_110, 0).unwrap(), 110);
+        assert_eq!(v.payable_yield(1_110, 1_000).unwrap(), 100);
+    }
+
+    #[test]
+    fn new_yield_raises_the_index_per_unit_locked() {
+        let mut v = vault(50, 2000, false, 0);
+        v.allot_yield(100).unwrap();
+        assert_eq!((v.yield_index, v.allotted_yield), (0, 0));
+
+        // 100 over 4,000 locked is 0.025 per unit
+        v.total_locked = 4_000;
+        v.allot_yield(100).unwrap();
+        assert_eq!((v.yield_index, v.allotted_yield), (YIELD_INDEX_SCALE / 40, 100));
+
+        // Only yield above what is already allotted is new
+        v.allot_yield(90).unwrap();
+        assert_eq!((v.yield_index, v.allotted_yield), (YIELD_INDEX_SCALE / 40, 100));
+        v.allot_yield(140).unwrap();
+        assert_eq!((v.yield_index, v.allotted_yield), (YIELD_INDEX_SCALE * 35 / 1000, 140));
+
+        v.unallot_yield(40).unwrap();
+        assert!(v.unallot_yield(101).is_err());
+    }
+
+    #[test]
+    fn spot_apy_is_capped() {
+        let v = vault(50, 2000, false, 0);
+        assert_eq!(v.conservative_apy_bps(800).unwrap(), 800);
+        assert_eq!(v.conservative_apy_bps(9000).unwrap(), 2000);
+    }
+
+    #[test]
+    fn apy_below_floor_is_rejected() {
+        let v = vault(50, 2000, false, 0);
+        assert!(v.conservative_apy_bps(49).is_err());
+        assert!(v.conservative_apy_bps(0).is_err());
+    }
+
+    #[test]
+    fn smoothed_apy_bounds_spikes() {
+        let v = vault(50, 2000, true, 600);
+        assert_eq!(v.conservative_apy_bps(1500).unwrap(), 600);
+        assert_eq!(v.conservative_apy_bps(400).unwrap(), 400);
+    }
+
+    #[test]
+    fn ema_moves_towards_spot_and_is_rate_limited() {
+        let mut v = vault(50, 2000, true, 0);
+        v.update_smoothed_apy(500, 1_000).unwrap();
+        assert_eq!(v.smoothed_apy_bps, 500);
+
+        assert!(v.update_smoothed_apy(5000, 1_001).is_err());
+
+        // spot is capped to 2000 before averaging: 0.1 * 2000 + 0.9 * 500
+        v.update_smoothed_apy(5000, 1_000 + APY_REFRESH_INTERVAL).unwrap();
+        assert_eq!(v.smoothed_apy_bps, 650);
+    }
+}
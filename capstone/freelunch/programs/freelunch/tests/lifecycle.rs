@@ -0,0 +1,1441 @@
+//! Purchases, invoices, payouts, collateral health, factoring and account cleanup
+//! against the mock lending program, on an in-process runtime. Run with `cargo test -p freelunch`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    entrypoint::ProgramResult, instruction::Instruction, program_pack::Pack, system_instruction, system_program,
+};
+use anchor_lang::{AccountDeserialize, InstructionData};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use freelunch::constants::{FACTORING_DEAD_SHARES, PURCHASE_INTENT_DOMAIN};
+use freelunch::error::ErrorCode;
+use freelunch::instructions::{CollateralHealth, DisputeResolution};
+use freelunch::solend::{collateral_to_liquidity, deposit_apy_bps};
+use freelunch::state::{
+    BuyerAccount, FactoringPool, Installment, Invoice, MerchantAccount, PaymentStatus, ProofOfFuturePayment, ProtocolVault,
+    PurchaseIntent,
+};
+use mock_lending::instruction::lending_market_authority;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solend_sdk::state::{Reserve, SLOTS_PER_YEAR};
+
+const USDC: u64 = 1_000_000;
+const BUYER_FUNDS: u64 = 2_000 * USDC;
+const LENDER_DEPOSIT: u64 = 1_000_000 * USDC;
+const LP_FUNDS: u64 = 1_000 * USDC;
+const BORROWED: u64 = 800_000 * USDC;
+const BORROW_RATE_PCT: u8 = 10;
+
+const PAYMENT: u64 = 100 * USDC;
+const BUFFER_BPS: u64 = 500;
+
+// Anchor's entrypoint wants accounts that live as long as 'info; leak them so
+// the test runtime's shorter borrow fits.
+fn freelunch_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    freelunch::entry(program_id, accounts, data)
+}
+
+struct Env {
+    ctx: ProgramTestContext,
+    admin: Keypair,
+    buyer: Keypair,
+    merchant: Keypair,
+    keeper: Keypair,
+    lender: Keypair,
+    lp: Keypair,
+    usdc_mint: Pubkey,
+    reserve: Pubkey,
+    lending_market: Pubkey,
+    reserve_liquidity_supply: Pubkey,
+    reserve_collateral_mint: Pubkey,
+    buyer_usdc: Pubkey,
+    merchant_usdc: Pubkey,
+    keeper_usdc: Pubkey,
+    lender_usdc: Pubkey,
+    lender_collateral: Pubkey,
+    lp_usdc: Pubkey,
+    protocol_usdc: Pubkey,
+    protocol_collateral: Pubkey,
+}
+
+fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &freelunch::ID).0
+}
+
+fn protocol_vault() -> Pubkey {
+    pda(&[b"protocol_vault"])
+}
+
+fn treasury() -> Pubkey {
+    pda(&[b"treasury"])
+}
+
+fn buyer_account(buyer: &Pubkey) -> Pubkey {
+    pda(&[b"buyer", buyer.as_ref()])
+}
+
+fn merchant_account(merchant: &Pubkey) -> Pubkey {
+    pda(&[b"merchant", merchant.as_ref()])
+}
+
+fn proof_of_payment(buyer: &Pubkey, merchant: &Pubkey, payment_number: u64) -> Pubkey {
+    pda(&[b"proof_of_payment", buyer.as_ref(), merchant.as_ref(), &payment_number.to_le_bytes()])
+}
+
+fn invoice(merchant: &Pubkey, invoice_number: u64) -> Pubkey {
+    pda(&[b"invoice", merchant.as_ref(), &invoice_number.to_le_bytes()])
+}
+
+fn factoring_pool() -> Pubkey {
+    pda(&[b"factoring_pool"])
+}
+
+fn factoring_liquidity() -> Pubkey {
+    pda(&[b"factoring_liquidity"])
+}
+
+fn freelunch_ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: freelunch::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn packed<T: Pack>(state: T, owner: Pubkey) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(state, &mut data).unwrap();
+    Account {
+        lamports: Rent::default().minimum_balance(T::LEN),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn mint(authority: Pubkey, supply: u64) -> Account {
+    packed(
+        spl_token::state::Mint {
+            mint_authority: Some(authority).into(),
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        },
+        spl_token::ID,
+    )
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    packed(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        },
+        spl_token::ID,
+    )
+}
+
+/// The Ed25519 precompile instruction carrying `signer`'s signature over `message`.
+fn ed25519_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    const HEADER: u16 = 16; // count, padding and one set of offsets
+    let public_key_offset = HEADER;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        u16::MAX, // Everything lives in this instruction
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: solana_sdk::ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+fn custom_error(err: BanksClientError) -> u32 {
+    match err.unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("expected a custom program error, got {err:?}"),
+    }
+}
+
+impl Env {
+    /// A reserve with 1M USDC deposited by an outside lender, 80% of it borrowed
+    /// at 10% a year, and a vault initialised against it. The buyer holds
+    /// `BUYER_FUNDS` USDC and the merchant is registered.
+    async fn new() -> Self {
+        let mut pt = ProgramTest::new("freelunch", freelunch::ID, processor!(freelunch_entry));
+        pt.add_program("mock_lending", mock_lending::ID, processor!(mock_lending::processor::process_instruction));
+        pt.prefer_bpf(false);
+
+        let [admin, buyer, merchant, keeper, lender, lp] = [(); 6].map(|_| Keypair::new());
+        for user in [&admin, &buyer, &merchant, &keeper, &lender, &lp] {
+            pt.add_account(user.pubkey(), Account::new(10_000_000_000, 0, &system_program::ID));
+        }
+
+        let lending_market = Pubkey::new_unique();
+        let (market_authority, _) = lending_market_authority(&mock_lending::ID, &lending_market);
+        let usdc_mint = Pubkey::new_unique();
+        let reserve_collateral_mint = Pubkey::new_unique();
+        pt.add_account(usdc_mint, mint(admin.pubkey(), BUYER_FUNDS + LENDER_DEPOSIT + LP_FUNDS));
+        pt.add_account(reserve_collateral_mint, mint(market_authority, 0));
+
+        let mut token = |mint, owner, amount| {
+            let key = Pubkey::new_unique();
+            pt.add_account(key, token_account(mint, owner, amount));
+            key
+        };
+        let reserve_liquidity_supply = token(usdc_mint, market_authority, 0);
+        let buyer_usdc = token(usdc_mint, buyer.pubkey(), BUYER_FUNDS);
+        let merchant_usdc = token(usdc_mint, merchant.pubkey(), 0);
+        let keeper_usdc = token(usdc_mint, keeper.pubkey(), 0);
+        let lender_usdc = token(usdc_mint, lender.pubkey(), LENDER_DEPOSIT);
+        let lender_collateral = token(reserve_collateral_mint, lender.pubkey(), 0);
+        let lp_usdc = token(usdc_mint, lp.pubkey(), LP_FUNDS);
+        let protocol_collateral = token(reserve_collateral_mint, protocol_vault(), 0);
+        let borrower_usdc = token(usdc_mint, Pubkey::new_unique(), 0);
+        // Settlement swaps need the vault's USDC account to be its ATA
+        let protocol_usdc = get_associated_token_address(&protocol_vault(), &usdc_mint);
+        pt.add_account(protocol_usdc, token_account(usdc_mint, protocol_vault(), 0));
+
+        let reserve = Pubkey::new_unique();
+        pt.add_account(
+            reserve,
+            Account {
+                lamports: Rent::default().minimum_balance(Reserve::LEN),
+                data: vec![0; Reserve::LEN],
+                owner: mock_lending::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+
+        let mut env = Env {
+            ctx: pt.start_with_context().await,
+            admin,
+            buyer,
+            merchant,
+            keeper,
+            lender,
+            lp,
+            usdc_mint,
+            reserve,
+            lending_market,
+            reserve_liquidity_supply,
+            reserve_collateral_mint,
+            buyer_usdc,
+            merchant_usdc,
+            keeper_usdc,
+            lender_usdc,
+            lender_collateral,
+            lp_usdc,
+            protocol_usdc,
+            protocol_collateral,
+        };
+
+        // The outside lender supplies most of the reserve and a borrower takes 80%
+        let lender = env.lender.insecure_clone();
+        env.send(
+            &[
+                mock_lending::instruction::init_reserve(
+                    mock_lending::ID,
+                    BORROW_RATE_PCT,
+                    reserve,
+                    lending_market,
+                    usdc_mint,
+                    reserve_liquidity_supply,
+                    reserve_collateral_mint,
+                ),
+                solend_sdk::instruction::deposit_reserve_liquidity(
+                    mock_lending::ID,
+                    LENDER_DEPOSIT,
+                    env.lender_usdc,
+                    env.lender_collateral,
+                    reserve,
+                    reserve_liquidity_supply,
+                    reserve_collateral_mint,
+                    lending_market,
+                    lender.pubkey(),
+                ),
+                mock_lending::instruction::borrow(
+                    mock_lending::ID,
+                    BORROWED,
+                    reserve,
+                    reserve_liquidity_supply,
+                    borrower_usdc,
+                    lending_market,
+                ),
+            ],
+            &[&lender],
+        )
+        .await
+        .unwrap();
+
+        let admin = env.admin.insecure_clone();
+        env.send(
+            &[freelunch_ix(
+                freelunch::accounts::Initialize {
+                    protocol_vault: protocol_vault(),
+                    admin: admin.pubkey(),
+                    usdc_mint,
+                    treasury: treasury(),
+                    protocol_usdc_account: protocol_usdc,
+                    protocol_collateral_account: protocol_collateral,
+                    solend_program: mock_lending::ID,
+                    solend_reserve: reserve,
+                    token_program: spl_token::ID,
+                    system_program: system_program::ID,
+                },
+                freelunch::instruction::Init {},
+            )],
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+        let merchant = env.merchant.insecure_clone();
+        env.send(
+            &[freelunch_ix(
+                freelunch::accounts::MerchantInit {
+                    merchant: merchant.pubkey(),
+                    merchant_account: merchant_account(&merchant.pubkey()),
+                    system_program: system_program::ID,
+                },
+                freelunch::instruction::MerchantInit { seed: 1 },
+            )],
+            &[&merchant],
+        )
+        .await
+        .unwrap();
+
+        env
+    }
+
+    async fn send(&mut self, ixs: &[Instruction], signers: &[&Keypair]) -> std::result::Result<(), BanksClientError> {
+        let payer = self.ctx.payer.insecure_clone();
+        let mut all_signers = vec![&payer];
+        all_signers.extend_from_slice(signers);
+        // A fresh blockhash keeps a retried identical transaction from being deduplicated
+        let blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(ixs, Some(&payer.pubkey()), &all_signers, blockhash);
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    async fn account<T: AccountDeserialize>(&mut self, key: Pubkey) -> T {
+        let account = self.ctx.banks_client.get_account(key).await.unwrap().unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn balance(&mut self, key: Pubkey) -> u64 {
+        let account = self.ctx.banks_client.get_account(key).await.unwrap().unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    async fn load_reserve(&mut self) -> Reserve {
+        let account = self.ctx.banks_client.get_account(self.reserve).await.unwrap().unwrap();
+        Reserve::unpack(&account.data).unwrap()
+    }
+
+    /// USDC the vault's cUSDC is worth at the reserve's last-accrued rate.
+    async fn vault_value(&mut self) -> u64 {
+        let reserve = self.load_reserve().await;
+        let collateral = self.balance(self.protocol_collateral).await;
+        collateral_to_liquidity(&reserve, collateral).unwrap()
+    }
+
+    async fn now(&mut self) -> i64 {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    async fn set_time(&mut self, unix_timestamp: i64) {
+        let clock = self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+        self.ctx.set_sysvar(&Clock { unix_timestamp, ..clock });
+    }
+
+    async fn warp_slots(&mut self, slots: u64) {
+        let slot = self.ctx.banks_client.get_root_slot().await.unwrap();
+        self.ctx.warp_to_slot(slot + slots).unwrap();
+        let refresh = mock_lending::instruction::refresh_reserve(mock_lending::ID, self.reserve);
+        self.send(&[refresh], &[]).await.unwrap();
+    }
+
+    fn solend_accounts(&self) -> (Pubkey, Pubkey) {
+        (lending_market_authority(&mock_lending::ID, &self.lending_market).0, self.lending_market)
+    }
+
+    async fn stake(&mut self, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let buyer = self.buyer.insecure_clone();
+        let (lending_market_authority, lending_market) = self.solend_accounts();
+        let ix = freelunch_ix(
+            freelunch::accounts::StakeAsset {
+                buyer: buyer.pubkey(),
+                buyer_usdc_account: self.buyer_usdc,
+                buyer_account: buyer_account(&buyer.pubkey()),
+                protocol_vault: protocol_vault(),
+                solend_program: mock_lending::ID,
+                solend_reserve: self.reserve,
+                reserve_liquidity_supply: self.reserve_liquidity_supply,
+                reserve_collateral_mint: self.reserve_collateral_mint,
+                lending_market,
+                lending_market_authority,
+                protocol_collateral_account: self.protocol_collateral,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::Stake { amount },
+        );
+        self.send(&[ix], &[&buyer]).await
+    }
+
+    async fn unstake(&mut self, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let buyer = self.buyer.insecure_clone();
+        let (lending_market_authority, lending_market) = self.solend_accounts();
+        let ix = freelunch_ix(
+            freelunch::accounts::Withdraw {
+                buyer: buyer.pubkey(),
+                buyer_account: buyer_account(&buyer.pubkey()),
+                protocol_vault: protocol_vault(),
+                protocol_usdc_account: self.protocol_usdc,
+                buyer_usdc_account: self.buyer_usdc,
+                protocol_collateral_account: self.protocol_collateral,
+                solend_program: mock_lending::ID,
+                solend_reserve: self.reserve,
+                reserve_liquidity_supply: self.reserve_liquidity_supply,
+                reserve_collateral_mint: self.reserve_collateral_mint,
+                lending_market,
+                lending_market_authority,
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::Unstake { amount },
+        );
+        self.send(&[ix], &[&buyer]).await
+    }
+
+    /// Relays a buyer-signed lump-sum purchase and returns the new PoF.
+    async fn purchase(&mut self, amount: u64) -> Pubkey {
+        self.purchase_in_installments(amount, vec![]).await
+    }
+
+    async fn purchase_in_installments(&mut self, amount: u64, installments: Vec<Installment>) -> Pubkey {
+        let (proof, intent, ix) = self.purchase_ix(amount, installments).await;
+        let admin = self.admin.insecure_clone();
+        let intent_ix = ed25519_ix(&self.buyer, &intent.to_slice());
+        self.send(&[intent_ix, ix], &[&admin]).await.unwrap();
+        proof
+    }
+
+    /// The PoF address, the intent the buyer has to sign and the purchase instruction.
+    async fn purchase_ix(&mut self, amount: u64, installments: Vec<Installment>) -> (Pubkey, PurchaseIntent, Instruction) {
+        let (admin, buyer, merchant) = (self.admin.pubkey(), self.buyer.pubkey(), self.merchant.pubkey());
+        let buyer_state: BuyerAccount = self.account(buyer_account(&buyer)).await;
+        let payment_number = self.account::<MerchantAccount>(merchant_account(&merchant)).await.payment_number;
+        let proof = proof_of_payment(&buyer, &merchant, payment_number);
+
+        let intent = PurchaseIntent {
+            buyer,
+            account_number: buyer_state.account_number,
+            merchant,
+            amount,
+            buffer_bps: BUFFER_BPS,
+            nonce: buyer_state.purchase_nonce,
+            expiry: i64::MAX,
+            installments: installments.clone(),
+        };
+        let ix = freelunch_ix(
+            freelunch::accounts::CreateProofOfPayment {
+                admin,
+                buyer_account: buyer_account(&buyer),
+                proof_of_payment: proof,
+                merchant_account: merchant_account(&merchant),
+                protocol_vault: protocol_vault(),
+                merchant,
+                solend_reserve: self.reserve,
+                instruction_sysvar: anchor_lang::solana_program::sysvar::instructions::ID,
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::CreateProofOfPayment {
+                purchase_amount: amount,
+                buffer_bps: BUFFER_BPS,
+                nonce: intent.nonce,
+                expiry: intent.expiry,
+                installments,
+            },
+        );
+        (proof, intent, ix)
+    }
+
+    /// Issues an invoice for `amount` and returns its address.
+    async fn create_invoice(&mut self, amount: u64, expires_at: i64) -> Pubkey {
+        let merchant = self.merchant.insecure_clone();
+        let invoice_number = self.account::<MerchantAccount>(merchant_account(&merchant.pubkey())).await.payment_number;
+        let invoice = invoice(&merchant.pubkey(), invoice_number);
+        let ix = freelunch_ix(
+            freelunch::accounts::CreateInvoice {
+                merchant: merchant.pubkey(),
+                merchant_account: merchant_account(&merchant.pubkey()),
+                invoice,
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::CreateInvoice { amount, memo_hash: [7; 32], expires_at },
+        );
+        self.send(&[ix], &[&merchant]).await.unwrap();
+        invoice
+    }
+
+    async fn cancel_invoice(&mut self, invoice: Pubkey, authority: &Keypair) -> std::result::Result<(), BanksClientError> {
+        let ix = freelunch_ix(
+            freelunch::accounts::CancelInvoice {
+                authority: authority.pubkey(),
+                invoice,
+                merchant: self.merchant.pubkey(),
+            },
+            freelunch::instruction::CancelInvoice {},
+        );
+        self.send(&[ix], &[authority]).await
+    }
+
+    async fn accept_invoice(&mut self, invoice: Pubkey, invoice_number: u64) -> std::result::Result<(), BanksClientError> {
+        let buyer = self.buyer.insecure_clone();
+        let merchant = self.merchant.pubkey();
+        let ix = freelunch_ix(
+            freelunch::accounts::AcceptInvoice {
+                buyer: buyer.pubkey(),
+                buyer_account: buyer_account(&buyer.pubkey()),
+                invoice,
+                proof_of_payment: proof_of_payment(&buyer.pubkey(), &merchant, invoice_number),
+                merchant_account: merchant_account(&merchant),
+                protocol_vault: protocol_vault(),
+                merchant,
+                solend_reserve: self.reserve,
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::AcceptInvoice { buffer_bps: BUFFER_BPS, installments: vec![] },
+        );
+        self.send(&[ix], &[&buyer]).await
+    }
+
+    async fn top_up(&mut self, proof: Pubkey, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let buyer = self.buyer.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::TopUpCollateral {
+                buyer: buyer.pubkey(),
+                buyer_account: buyer_account(&buyer.pubkey()),
+                proof_of_payment: proof,
+                protocol_vault: protocol_vault(),
+            },
+            freelunch::instruction::TopUpCollateral { amount },
+        );
+        self.send(&[ix], &[&buyer]).await
+    }
+
+    /// Simulates `check_health` and decodes its return data.
+    async fn health(&mut self, proof: Pubkey) -> CollateralHealth {
+        let ix = freelunch_ix(
+            freelunch::accounts::CheckHealth {
+                proof_of_payment: proof,
+                protocol_vault: protocol_vault(),
+                solend_reserve: self.reserve,
+            },
+            freelunch::instruction::CheckHealth {},
+        );
+        let payer = self.ctx.payer.insecure_clone();
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await.unwrap();
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], blockhash);
+        let simulation = self.ctx.banks_client.simulate_transaction(tx).await.unwrap();
+        simulation.result.unwrap().unwrap();
+        let return_data = simulation.simulation_details.unwrap().return_data.unwrap();
+        CollateralHealth::try_from_slice(&return_data.data).unwrap()
+    }
+
+    async fn init_factoring_pool(&mut self, discount_bps: u64) {
+        let admin = self.admin.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::InitFactoringPool {
+                admin: admin.pubkey(),
+                protocol_vault: protocol_vault(),
+                usdc_mint: self.usdc_mint,
+                factoring_pool: factoring_pool(),
+                factoring_liquidity: factoring_liquidity(),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::InitFactoringPool { discount_bps },
+        );
+        self.send(&[ix], &[&admin]).await.unwrap();
+    }
+
+    fn factoring_liquidity_accounts(&self) -> freelunch::accounts::FactoringLiquidity {
+        freelunch::accounts::FactoringLiquidity {
+            lp: self.lp.pubkey(),
+            lp_usdc_account: self.lp_usdc,
+            liquidity_position: pda(&[b"liquidity_position", self.lp.pubkey().as_ref()]),
+            factoring_pool: factoring_pool(),
+            factoring_liquidity: factoring_liquidity(),
+            protocol_vault: protocol_vault(),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    async fn deposit_liquidity(&mut self, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let lp = self.lp.insecure_clone();
+        let ix = freelunch_ix(
+            self.factoring_liquidity_accounts(),
+            freelunch::instruction::DepositFactoringLiquidity { amount },
+        );
+        self.send(&[ix], &[&lp]).await
+    }
+
+    async fn withdraw_liquidity(&mut self, shares: u64) -> std::result::Result<(), BanksClientError> {
+        let lp = self.lp.insecure_clone();
+        let ix = freelunch_ix(
+            self.factoring_liquidity_accounts(),
+            freelunch::instruction::WithdrawFactoringLiquidity { shares },
+        );
+        self.send(&[ix], &[&lp]).await
+    }
+
+    async fn factor(&mut self, proof: Pubkey) -> std::result::Result<(), BanksClientError> {
+        let merchant = self.merchant.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::FactorPayment {
+                merchant: merchant.pubkey(),
+                merchant_account: merchant_account(&merchant.pubkey()),
+                proof_of_payment: proof,
+                merchant_usdc_account: self.merchant_usdc,
+                factoring_pool: factoring_pool(),
+                factoring_liquidity: factoring_liquidity(),
+                protocol_vault: protocol_vault(),
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::MerchantFactorPayment { min_payout: 0 },
+        );
+        self.send(&[ix], &[&merchant]).await
+    }
+
+    async fn collect(&mut self, proof: Pubkey) -> std::result::Result<(), BanksClientError> {
+        let ix = freelunch_ix(
+            freelunch::accounts::CollectFactoredPayment {
+                proof_of_payment: proof,
+                factoring_pool: factoring_pool(),
+                factoring_liquidity: factoring_liquidity(),
+                protocol_usdc_account: self.protocol_usdc,
+                protocol_vault: protocol_vault(),
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::CollectFactoredPayment {},
+        );
+        self.send(&[ix], &[]).await
+    }
+
+    async fn close_proof_of_payment(&mut self, proof: Pubkey) -> std::result::Result<(), BanksClientError> {
+        let keeper = self.keeper.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::CloseProofOfPayment {
+                authority: keeper.pubkey(),
+                proof_of_payment: proof,
+                rent_receiver: self.admin.pubkey(),
+            },
+            freelunch::instruction::CloseProofOfPayment {},
+        );
+        self.send(&[ix], &[&keeper]).await
+    }
+
+    async fn close_buyer_account(&mut self) -> std::result::Result<(), BanksClientError> {
+        let buyer = self.buyer.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::CloseBuyerAccount {
+                buyer: buyer.pubkey(),
+                buyer_account: buyer_account(&buyer.pubkey()),
+            },
+            freelunch::instruction::CloseBuyerAccount {},
+        );
+        self.send(&[ix], &[&buyer]).await
+    }
+
+    async fn harvest(&mut self, proofs: &[Pubkey]) -> std::result::Result<(), BanksClientError> {
+        let keeper = self.keeper.insecure_clone();
+        let (lending_market_authority, lending_market) = self.solend_accounts();
+        let mut ix = freelunch_ix(
+            freelunch::accounts::HarvestAndPay {
+                keeper: keeper.pubkey(),
+                keeper_usdc_account: self.keeper_usdc,
+                protocol_vault: protocol_vault(),
+                protocol_usdc_account: self.protocol_usdc,
+                protocol_collateral_account: self.protocol_collateral,
+                treasury: treasury(),
+                solend_program: mock_lending::ID,
+                solend_reserve: self.reserve,
+                reserve_liquidity_supply: self.reserve_liquidity_supply,
+                reserve_collateral_mint: self.reserve_collateral_mint,
+                lending_market,
+                lending_market_authority,
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::HarvestAndPay {},
+        );
+        let buyer_account = buyer_account(&self.buyer.pubkey());
+        for proof in proofs {
+            ix.accounts.push(AccountMeta::new(*proof, false));
+            ix.accounts.push(AccountMeta::new(buyer_account, false));
+        }
+        self.send(&[ix], &[&keeper]).await
+    }
+
+    async fn fulfill(&mut self, proof: Pubkey, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let admin = self.admin.insecure_clone();
+        let (lending_market_authority, lending_market) = self.solend_accounts();
+        let ix = freelunch_ix(
+            freelunch::accounts::FulfillProofOfPayment {
+                protocol_signer: admin.pubkey(),
+                protocol_vault: protocol_vault(),
+                protocol_usdc_account: self.protocol_usdc,
+                merchant_usdc_account: self.merchant_usdc,
+                proof_of_payment: proof,
+                buyer_account: buyer_account(&self.buyer.pubkey()),
+                merchant_account: merchant_account(&self.merchant.pubkey()),
+                solend_program: mock_lending::ID,
+                solend_reserve: self.reserve,
+                reserve_liquidity_supply: self.reserve_liquidity_supply,
+                reserve_collateral_mint: self.reserve_collateral_mint,
+                lending_market,
+                lending_market_authority,
+                protocol_collateral_account: self.protocol_collateral,
+                treasury: treasury(),
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::FulfillProofOfPayment { amount_to_pay_now: amount, min_amount_out: 0 },
+        );
+        self.send(&[ix], &[&admin]).await
+    }
+
+    async fn open_dispute(&mut self, proof: Pubkey) {
+        let buyer = self.buyer.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::OpenDispute { buyer: buyer.pubkey(), proof_of_payment: proof },
+            freelunch::instruction::OpenDispute {},
+        );
+        self.send(&[ix], &[&buyer]).await.unwrap();
+    }
+
+    async fn resolve_dispute(&mut self, proof: Pubkey, resolution: DisputeResolution) -> std::result::Result<(), BanksClientError> {
+        let admin = self.admin.insecure_clone();
+        let factored = self.account::<ProofOfFuturePayment>(proof).await.factored;
+        let ix = freelunch_ix(
+            freelunch::accounts::ResolveDispute {
+                arbiter: admin.pubkey(),
+                proof_of_payment: proof,
+                buyer_account: buyer_account(&self.buyer.pubkey()),
+                protocol_vault: protocol_vault(),
+                factoring_pool: factored.then(factoring_pool),
+            },
+            freelunch::instruction::ResolveDispute { resolution },
+        );
+        self.send(&[ix], &[&admin]).await
+    }
+
+    async fn cancel(&mut self, proof: Pubkey, authority: &Keypair, merchant: Option<&Keypair>) -> std::result::Result<(), BanksClientError> {
+        let ix = freelunch_ix(
+            freelunch::accounts::CancelProofOfPayment {
+                authority: authority.pubkey(),
+                merchant: merchant.map(|merchant| merchant.pubkey()),
+                proof_of_payment: proof,
+                buyer_account: buyer_account(&self.buyer.pubkey()),
+                protocol_vault: protocol_vault(),
+                rent_receiver: self.admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            freelunch::instruction::CancelProofOfPayment {},
+        );
+        let mut signers = vec![authority];
+        signers.extend(merchant);
+        self.send(&[ix], &signers).await
+    }
+
+    async fn claim(&mut self, proof: Pubkey, amount: u64) -> std::result::Result<(), BanksClientError> {
+        let merchant = self.merchant.insecure_clone();
+        let ix = freelunch_ix(
+            freelunch::accounts::MerchantClaim {
+                merchant: merchant.pubkey(),
+                proof_of_payment: proof,
+                protocol_usdc_account: self.protocol_usdc,
+                merchant_usdc_account: self.merchant_usdc,
+                merchant_account: merchant_account(&merchant.pubkey()),
+                protocol_vault: protocol_vault(),
+                token_program: spl_token::ID,
+            },
+            freelunch::instruction::MerchantClaim { amount_to_claim: amount, min_amount_out: 0 },
+        );
+        self.send(&[ix], &[&merchant]).await
+    }
+}
+
+#[tokio::test]
+async fn stake_mints_collateral_and_pins_the_reserve() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert_eq!(vault.solend_program, mock_lending::ID);
+    assert_eq!(vault.solend_reserve, env.reserve);
+    assert_eq!(vault.reserve_liquidity_supply, env.reserve_liquidity_supply);
+    assert_eq!(vault.reserve_collateral_mint, env.reserve_collateral_mint);
+    assert_eq!(vault.lending_market, env.lending_market);
+    assert_eq!((vault.usdc_account, vault.collateral_account), (env.protocol_usdc, env.protocol_collateral));
+    assert_eq!(vault.total_staked, BUYER_FUNDS);
+
+    // Nothing has accrued yet, so the stake is worth exactly what went in
+    assert_eq!(env.balance(env.buyer_usdc).await, 0);
+    assert_eq!(env.vault_value().await, BUYER_FUNDS);
+
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!((buyer.staked_amount, buyer.unlockable_amount), (BUYER_FUNDS, BUYER_FUNDS));
+}
+
+#[tokio::test]
+async fn only_the_pinned_protocol_accounts_are_accepted() {
+    let mut env = Env::new().await;
+
+    // A second cUSDC account the vault owns, but not the one recorded at init
+    let admin = env.admin.insecure_clone();
+    let stray = Keypair::new();
+    let len = spl_token::state::Account::LEN;
+    env.send(
+        &[
+            system_instruction::create_account(
+                &admin.pubkey(),
+                &stray.pubkey(),
+                Rent::default().minimum_balance(len),
+                len as u64,
+                &spl_token::ID,
+            ),
+            spl_token::instruction::initialize_account3(&spl_token::ID, &stray.pubkey(), &env.reserve_collateral_mint, &protocol_vault())
+                .unwrap(),
+        ],
+        &[&admin, &stray],
+    )
+    .await
+    .unwrap();
+
+    let pinned = std::mem::replace(&mut env.protocol_collateral, stray.pubkey());
+    let err = env.stake(BUYER_FUNDS).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InvalidProtocolAccount));
+
+    env.protocol_collateral = pinned;
+    env.stake(BUYER_FUNDS).await.unwrap();
+}
+
+#[tokio::test]
+async fn harvest_pays_the_merchant_from_yield_accrued_over_time() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+
+    // Collateral is sized from the reserve's deposit APY (~8% here) plus the buffer
+    let apy_bps = deposit_apy_bps(&env.load_reserve().await).unwrap();
+    assert!((790..=800).contains(&apy_bps), "apy {apy_bps}");
+    let proof = env.purchase(PAYMENT).await;
+
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    let locked = PAYMENT * 10_000 / apy_bps * (10_000 + BUFFER_BPS) / 10_000;
+    assert_eq!(pof.status, PaymentStatus::Pending);
+    assert_eq!(pof.locked_collateral, locked);
+
+    // Locked collateral can't be withdrawn
+    let err = env.unstake(BUYER_FUNDS).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InsufficientFunds));
+
+    // Merchants can't claim ahead of yield, and there's nothing to harvest yet
+    let err = env.claim(proof, PAYMENT).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::NothingToClaim));
+    let err = env.harvest(&[proof]).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::NoYieldToHarvest));
+
+    // A year of interest at ~8% on 2,000 USDC covers the 100 USDC payment and the fees
+    env.warp_slots(SLOTS_PER_YEAR).await;
+    let accrued = env.vault_value().await - BUYER_FUNDS;
+    assert!(accrued > PAYMENT * 3 / 2, "accrued {accrued}");
+
+    env.harvest(&[proof]).await.unwrap();
+
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!(pof.status, PaymentStatus::Completed);
+    assert_eq!(pof.amount_fulfilled, PAYMENT);
+    assert_eq!(pof.earned_unclaimed, PAYMENT);
+    assert_eq!(env.balance(env.keeper_usdc).await, PAYMENT * 50 / 10_000);
+    assert_eq!(env.balance(treasury()).await, PAYMENT * 1_000 / 10_000);
+
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!((buyer.locked_amount, buyer.unlockable_amount), (0, BUYER_FUNDS));
+    assert_eq!((buyer.open_pofs, buyer.outstanding_amount), (0, 0));
+
+    env.claim(proof, PAYMENT).await.unwrap();
+    assert_eq!(env.balance(env.merchant_usdc).await, PAYMENT);
+    assert_eq!(env.account::<ProofOfFuturePayment>(proof).await.earned_unclaimed, 0);
+
+    // The buyer walks away with their full principal; the rest of the yield stays staked
+    env.unstake(BUYER_FUNDS).await.unwrap();
+    assert_eq!(env.balance(env.buyer_usdc).await, BUYER_FUNDS);
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert_eq!((vault.total_staked, vault.total_locked, vault.pending_payments), (0, 0, 0));
+}
+
+#[tokio::test]
+async fn settlement_swaps_need_the_protocol_token_accounts_to_exist() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+    env.warp_slots(SLOTS_PER_YEAR).await;
+    env.harvest(&[proof]).await.unwrap();
+
+    // The merchant settles through a pool trading USDC for a mint the vault has never held
+    let settlement_mint = Pubkey::new_unique();
+    let pool = Pubkey::new_unique();
+    let mut data = Vec::new();
+    amm::state::Config {
+        seed: 0,
+        authority: None,
+        mint_x: env.usdc_mint,
+        mint_y: settlement_mint,
+        fee: 30,
+        locked: false,
+        config_bump: 0,
+        lp_bump: 0,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    let pool_account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: amm::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    env.ctx.set_account(&pool, &pool_account.into());
+
+    let merchant = env.merchant.insecure_clone();
+    let ix = freelunch_ix(
+        freelunch::accounts::SetSettlementPreference {
+            merchant: merchant.pubkey(),
+            merchant_account: merchant_account(&merchant.pubkey()),
+            settlement_pool: Some(pool),
+        },
+        freelunch::instruction::SetSettlementPreference { settlement_mint: Some(settlement_mint) },
+    );
+    env.send(&[ix], &[&merchant]).await.unwrap();
+
+    // Without the vault's settlement ATA the AMM would have to charge the vault PDA for it
+    let mut ix = freelunch_ix(
+        freelunch::accounts::MerchantClaim {
+            merchant: merchant.pubkey(),
+            proof_of_payment: proof,
+            protocol_usdc_account: env.protocol_usdc,
+            merchant_usdc_account: env.merchant_usdc,
+            merchant_account: merchant_account(&merchant.pubkey()),
+            protocol_vault: protocol_vault(),
+            token_program: spl_token::ID,
+        },
+        freelunch::instruction::MerchantClaim { amount_to_claim: PAYMENT, min_amount_out: 1 },
+    );
+    let missing_settlement_ata = Pubkey::new_unique();
+    ix.accounts.extend([
+        AccountMeta::new_readonly(amm::ID, false),
+        AccountMeta::new_readonly(env.usdc_mint, false),
+        AccountMeta::new_readonly(settlement_mint, false),
+        AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(pool, false),
+        AccountMeta::new(env.protocol_usdc, false),
+        AccountMeta::new(missing_settlement_ata, false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(anchor_spl::associated_token::ID, false),
+        AccountMeta::new(Pubkey::new_unique(), false),
+    ]);
+    let err = env.send(&[ix], &[&merchant]).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::SettlementAccountMissing));
+}
+
+#[tokio::test]
+async fn fulfillment_pays_the_merchant_directly() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+
+    // Jump the exchange rate 10% instead of waiting for interest
+    let set_rate = mock_lending::instruction::set_exchange_rate(mock_lending::ID, 1_100_000_000_000_000_000, env.reserve);
+    env.send(&[set_rate], &[]).await.unwrap();
+    assert_eq!(env.vault_value().await, BUYER_FUNDS * 11 / 10);
+
+    env.fulfill(proof, PAYMENT).await.unwrap();
+
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!(pof.status, PaymentStatus::Completed);
+    assert_eq!(pof.earned_unclaimed, 0);
+    assert_eq!(env.balance(env.merchant_usdc).await, PAYMENT);
+    assert_eq!(env.balance(treasury()).await, PAYMENT * 1_000 / 10_000);
+
+    // Payment and fee came out of yield, not principal
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert_eq!((vault.total_locked, vault.pending_payments), (0, 0));
+    assert!(env.vault_value().await >= vault.total_staked);
+
+    let err = env.fulfill(proof, PAYMENT).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::PaymentAlreadyCompleted));
+}
+
+#[tokio::test]
+async fn fulfillment_is_capped_at_accrued_yield() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+
+    // Nothing has accrued yet, so paying now would come out of principal
+    let err = env.fulfill(proof, PAYMENT).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::NoYieldToHarvest));
+
+    // 11 USDC of yield covers about 10 USDC of payment plus the 10% protocol fee
+    let set_rate = mock_lending::instruction::set_exchange_rate(mock_lending::ID, 1_005_500_000_000_000_000, env.reserve);
+    env.send(&[set_rate], &[]).await.unwrap();
+    env.fulfill(proof, PAYMENT).await.unwrap();
+
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!(pof.status, PaymentStatus::PartiallyPaid);
+    assert!((10 * USDC - 3..=10 * USDC).contains(&pof.amount_fulfilled), "paid {}", pof.amount_fulfilled);
+    assert_eq!(env.balance(env.merchant_usdc).await, pof.amount_fulfilled);
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert!(env.vault_value().await >= vault.total_staked);
+}
+
+#[tokio::test]
+async fn harvest_splits_yield_pro_rata_to_locked_collateral() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let older = env.purchase(PAYMENT / 2).await;
+    let now = env.now().await;
+    env.set_time(now + 60).await;
+    let newer = env.purchase(PAYMENT / 2).await;
+
+    // 66.3 USDC of yield leaves about 60 USDC to credit after the 0.5% keeper and 10% protocol fees,
+    // and both PoFs lock the same collateral, so each is allotted half
+    let set_rate = mock_lending::instruction::set_exchange_rate(mock_lending::ID, 1_033_150_000_000_000_000, env.reserve);
+    env.send(&[set_rate], &[]).await.unwrap();
+    env.harvest(&[newer, older]).await.unwrap();
+
+    let older: ProofOfFuturePayment = env.account(older).await;
+    let newer: ProofOfFuturePayment = env.account(newer).await;
+    for proof in [&older, &newer] {
+        assert_eq!(proof.status, PaymentStatus::PartiallyPaid);
+        assert!((30 * USDC - 2..=30 * USDC).contains(&proof.earned_unclaimed), "credited {}", proof.earned_unclaimed);
+        assert_eq!(proof.allotted_yield, 0);
+    }
+    let credited = older.earned_unclaimed + newer.earned_unclaimed;
+    assert_eq!(env.balance(env.keeper_usdc).await, credited * 50 / 10_000);
+    assert_eq!(env.balance(treasury()).await, credited * 1_000 / 10_000);
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert!(env.vault_value().await >= vault.total_staked);
+}
+
+#[tokio::test]
+async fn skipping_an_older_payment_leaves_its_share_in_the_pool() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let older = env.purchase(PAYMENT / 2).await;
+    let now = env.now().await;
+    env.set_time(now + 60).await;
+    let newer = env.purchase(PAYMENT / 2).await;
+
+    let set_rate = mock_lending::instruction::set_exchange_rate(mock_lending::ID, 1_033_150_000_000_000_000, env.reserve);
+    env.send(&[set_rate], &[]).await.unwrap();
+
+    // A keeper passing only the newer PoF can't hand it the older one's half
+    env.harvest(&[newer]).await.unwrap();
+    let skipped: ProofOfFuturePayment = env.account(older).await;
+    let paid: ProofOfFuturePayment = env.account(newer).await;
+    assert_eq!((skipped.status, skipped.earned_unclaimed), (PaymentStatus::Pending, 0));
+    assert!((30 * USDC - 2..=30 * USDC).contains(&paid.earned_unclaimed), "credited {}", paid.earned_unclaimed);
+
+    // Its half waits for it, and the newer PoF has nothing left to take
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert!((30 * USDC - 2..=30 * USDC).contains(&vault.allotted_yield), "allotted {}", vault.allotted_yield);
+    let err = env.harvest(&[newer]).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::NoYieldToHarvest));
+
+    env.harvest(&[older]).await.unwrap();
+    let older: ProofOfFuturePayment = env.account(older).await;
+    assert!(older.earned_unclaimed.abs_diff(paid.earned_unclaimed) <= 2, "credited {}", older.earned_unclaimed);
+    assert!(env.account::<ProtocolVault>(protocol_vault()).await.allotted_yield <= 2);
+}
+
+#[tokio::test]
+async fn intents_only_verify_for_this_program() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let (_, intent, ix) = env.purchase_ix(PAYMENT, vec![]).await;
+    let admin = env.admin.insecure_clone();
+
+    // The same terms signed for another deployment, or without the domain prefix
+    let mut other_deployment = intent.to_slice();
+    let program_id_at = PURCHASE_INTENT_DOMAIN.len();
+    other_deployment[program_id_at..program_id_at + 32].copy_from_slice(Pubkey::new_unique().as_ref());
+    let undomained = intent.to_slice()[program_id_at + 32..].to_vec();
+    for message in [other_deployment, undomained] {
+        let intent_ix = ed25519_ix(&env.buyer, &message);
+        let err = env.send(&[intent_ix, ix.clone()], &[&admin]).await.unwrap_err();
+        assert_eq!(custom_error(err), u32::from(ErrorCode::InvalidPurchaseIntent));
+    }
+
+    let intent_ix = ed25519_ix(&env.buyer, &intent.to_slice());
+    env.send(&[intent_ix, ix], &[&admin]).await.unwrap();
+}
+
+#[tokio::test]
+async fn accepted_invoice_becomes_a_proof_of_payment() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+
+    let expires_at = env.now().await + 3_600;
+    let invoice = env.create_invoice(PAYMENT, expires_at).await;
+    let issued: Invoice = env.account(invoice).await;
+    assert_eq!((issued.invoice_number, issued.amount, issued.memo_hash), (0, PAYMENT, [7; 32]));
+
+    // The invoice took payment number 0, so the next direct purchase gets 1
+    let merchant = env.merchant.pubkey();
+    assert_eq!(env.account::<MerchantAccount>(merchant_account(&merchant)).await.payment_number, 1);
+
+    env.accept_invoice(invoice, 0).await.unwrap();
+    assert!(env.ctx.banks_client.get_account(invoice).await.unwrap().is_none());
+
+    let buyer = env.buyer.pubkey();
+    let pof: ProofOfFuturePayment = env.account(proof_of_payment(&buyer, &merchant, 0)).await;
+    assert_eq!((pof.payment_number, pof.payment_amount), (0, PAYMENT));
+    assert_eq!(pof.status, PaymentStatus::Pending);
+    assert_eq!(pof.admin, buyer);
+
+    let buyer_state: BuyerAccount = env.account(buyer_account(&buyer)).await;
+    assert_eq!(buyer_state.locked_amount, pof.locked_collateral);
+    assert_eq!((buyer_state.open_pofs, buyer_state.outstanding_amount), (1, PAYMENT));
+
+    let proof = env.purchase(PAYMENT / 2).await;
+    assert_eq!(env.account::<ProofOfFuturePayment>(proof).await.payment_number, 1);
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert_eq!(vault.pending_payments, PAYMENT * 3 / 2);
+}
+
+#[tokio::test]
+async fn invoices_can_be_cancelled_or_left_to_expire() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+
+    let expires_at = env.now().await + 3_600;
+    let withdrawn = env.create_invoice(PAYMENT, expires_at).await;
+    let lapsed = env.create_invoice(PAYMENT, expires_at).await;
+
+    // Only the merchant may withdraw a live invoice
+    let keeper = env.keeper.insecure_clone();
+    let err = env.cancel_invoice(withdrawn, &keeper).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::Unauthorized));
+
+    let merchant = env.merchant.insecure_clone();
+    env.cancel_invoice(withdrawn, &merchant).await.unwrap();
+    assert!(env.ctx.banks_client.get_account(withdrawn).await.unwrap().is_none());
+    let err = env.accept_invoice(withdrawn, 0).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(anchor_lang::error::ErrorCode::AccountNotInitialized));
+
+    // Past expiry the invoice can't be accepted, and anyone can close it
+    env.set_time(expires_at + 1).await;
+    let err = env.accept_invoice(lapsed, 1).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InvoiceExpired));
+
+    let merchant_lamports = env.ctx.banks_client.get_balance(merchant.pubkey()).await.unwrap();
+    env.cancel_invoice(lapsed, &keeper).await.unwrap();
+    assert!(env.ctx.banks_client.get_balance(merchant.pubkey()).await.unwrap() > merchant_lamports);
+
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!((buyer.locked_amount, buyer.open_pofs), (0, 0));
+}
+
+#[tokio::test]
+async fn topping_up_restores_a_payment_put_at_risk_by_falling_apy() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+
+    // Sized at ~8% plus the buffer, the lump sum pays off inside its year
+    let health = env.health(proof).await;
+    assert!(health.healthy);
+    assert!(health.required_collateral.unwrap() < health.locked_collateral);
+
+    // At 8% borrow the deposit APY falls to ~6.4% and the payoff slips past due
+    let set_rate = mock_lending::instruction::set_borrow_rate(mock_lending::ID, 8, env.reserve);
+    env.send(&[set_rate], &[]).await.unwrap();
+    let health = env.health(proof).await;
+    assert!(!health.healthy);
+    assert!(health.projected_payoff_at.unwrap() > health.due_at);
+    let shortfall = health.required_collateral.unwrap() - health.locked_collateral;
+
+    // Only unlockable stake can be moved into the lock
+    let unlockable = env.account::<BuyerAccount>(buyer_account(&env.buyer.pubkey())).await.unlockable_amount;
+    let err = env.top_up(proof, unlockable + 1).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InsufficientFunds));
+
+    env.top_up(proof, shortfall).await.unwrap();
+    let health = env.health(proof).await;
+    assert!(health.healthy);
+    assert_eq!(health.locked_collateral, health.required_collateral.unwrap());
+
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!(buyer.locked_amount, health.locked_collateral);
+    assert_eq!(buyer.unlockable_amount, unlockable - shortfall);
+    assert_eq!(env.account::<ProtocolVault>(protocol_vault()).await.total_locked, health.locked_collateral);
+}
+
+#[tokio::test]
+async fn factored_payment_pays_the_merchant_now_and_the_pool_from_yield() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+
+    env.init_factoring_pool(1_000).await;
+    env.deposit_liquidity(LP_FUNDS).await.unwrap();
+
+    // With about a year to go, a 10% discount rate takes about 10 USDC off
+    env.factor(proof).await.unwrap();
+    let payout = env.balance(env.merchant_usdc).await;
+    let discount = PAYMENT - payout;
+    assert!(discount > 0 && discount <= PAYMENT / 10, "discount {discount}");
+
+    let pool: FactoringPool = env.account(factoring_pool()).await;
+    assert_eq!((pool.receivables, pool.total_discount), (PAYMENT, discount));
+    assert!(env.account::<ProofOfFuturePayment>(proof).await.factored);
+
+    // The yield now belongs to the pool, so the merchant can't be paid twice
+    let err = env.factor(proof).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::PaymentFactored));
+    let err = env.fulfill(proof, PAYMENT).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::PaymentFactored));
+
+    // Receivables count towards the pool's value but can't be withdrawn yet
+    let lp_shares = LP_FUNDS - FACTORING_DEAD_SHARES;
+    let err = env.withdraw_liquidity(lp_shares).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InsufficientLiquidity));
+
+    env.warp_slots(SLOTS_PER_YEAR).await;
+    env.harvest(&[proof]).await.unwrap();
+    let err = env.claim(proof, PAYMENT).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::PaymentFactored));
+
+    env.collect(proof).await.unwrap();
+    let pool: FactoringPool = env.account(factoring_pool()).await;
+    assert_eq!((pool.receivables, pool.unearned_discount, pool.discount_rate), (0, 0, 0));
+    assert_eq!(env.balance(factoring_liquidity()).await, LP_FUNDS + discount);
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!((pof.status, pof.earned_unclaimed), (PaymentStatus::Completed, 0));
+
+    // The LP exits with their deposit plus the discount, bar the dead shares' cut
+    env.withdraw_liquidity(lp_shares).await.unwrap();
+    assert_eq!(env.balance(env.lp_usdc).await, lp_shares * (LP_FUNDS + discount) / LP_FUNDS);
+    assert_eq!(env.balance(env.merchant_usdc).await, payout);
+}
+
+#[tokio::test]
+async fn factored_payments_add_their_discount_to_the_pool_as_it_is_earned() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+    env.init_factoring_pool(1_000).await;
+
+    // The first LP gives up the dead shares, and can't deposit less than them
+    let err = env.deposit_liquidity(FACTORING_DEAD_SHARES).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InvalidPurchaseAmount));
+    env.deposit_liquidity(LP_FUNDS).await.unwrap();
+    assert_eq!(env.account::<FactoringPool>(factoring_pool()).await.total_shares, LP_FUNDS);
+
+    // Right after factoring the PoF is worth what the pool paid, not its face value
+    env.factor(proof).await.unwrap();
+    let discount = PAYMENT - env.balance(env.merchant_usdc).await;
+    env.withdraw_liquidity(100 * USDC).await.unwrap();
+    assert_eq!(env.balance(env.lp_usdc).await, 100 * USDC);
+
+    // Halfway to due, half the discount has been earned
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    env.set_time(pof.factored_at + (pof.due_at() - pof.factored_at) / 2).await;
+    env.withdraw_liquidity(100 * USDC).await.unwrap();
+    let remaining = (LP_FUNDS - 100 * USDC) as u128;
+    let expected = (100 * USDC) as u128 * (remaining + discount as u128 / 2) / remaining;
+    let received = (env.balance(env.lp_usdc).await - 100 * USDC) as u128;
+    assert!(received.abs_diff(expected) <= 1, "received {received}, expected {expected}");
+}
+
+#[tokio::test]
+async fn split_dispute_rescales_the_installment_plan() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let now = env.now().await;
+    let plan = [(90, PAYMENT / 4), (180, PAYMENT / 4), (365, PAYMENT / 2)]
+        .map(|(days, amount)| Installment { due_at: now + days * 86_400, amount });
+    let proof = env.purchase_in_installments(PAYMENT, plan.to_vec()).await;
+    let locked = env.account::<ProofOfFuturePayment>(proof).await.locked_collateral;
+
+    env.open_dispute(proof).await;
+    env.resolve_dispute(proof, DisputeResolution::Split { merchant_bps: 5_000 }).await.unwrap();
+
+    // Every installment is halved, so the plan still adds up to what is owed
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!(pof.status, PaymentStatus::Pending);
+    assert_eq!(pof.payment_amount, PAYMENT / 2);
+    let amounts: Vec<u64> = pof.installments.iter().map(|i| i.amount).collect();
+    assert_eq!(amounts, vec![PAYMENT / 8, PAYMENT / 8, PAYMENT / 4]);
+    assert_eq!(pof.current().unwrap().amount, PAYMENT / 8);
+    ProofOfFuturePayment::validate_schedule(&pof.installments, pof.payment_amount).unwrap();
+    assert_eq!(pof.locked_collateral, locked - locked / 2);
+
+    let vault: ProtocolVault = env.account(protocol_vault()).await;
+    assert_eq!(vault.pending_payments, PAYMENT / 2);
+
+    // Paying the reduced amount completes the PoF and frees the rest of the collateral
+    env.warp_slots(SLOTS_PER_YEAR).await;
+    env.fulfill(proof, PAYMENT / 2).await.unwrap();
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!((pof.status, pof.amount_fulfilled, pof.locked_collateral), (PaymentStatus::Completed, PAYMENT / 2, 0));
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!((buyer.locked_amount, buyer.open_pofs), (0, 0));
+}
+
+#[tokio::test]
+async fn refunding_a_factored_payment_is_a_loss_for_the_pool() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let proof = env.purchase(PAYMENT).await;
+    env.init_factoring_pool(1_000).await;
+    env.deposit_liquidity(LP_FUNDS).await.unwrap();
+    env.factor(proof).await.unwrap();
+    let payout = env.balance(env.merchant_usdc).await;
+
+    // The pool needs to be named so the forgiven amount comes off its receivables
+    env.open_dispute(proof).await;
+    let admin = env.admin.insecure_clone();
+    let ix = freelunch_ix(
+        freelunch::accounts::ResolveDispute {
+            arbiter: admin.pubkey(),
+            proof_of_payment: proof,
+            buyer_account: buyer_account(&env.buyer.pubkey()),
+            protocol_vault: protocol_vault(),
+            factoring_pool: None,
+        },
+        freelunch::instruction::ResolveDispute { resolution: DisputeResolution::RefundBuyer },
+    );
+    let err = env.send(&[ix], &[&admin]).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::FactoringPoolRequired));
+
+    env.resolve_dispute(proof, DisputeResolution::RefundBuyer).await.unwrap();
+    let pof: ProofOfFuturePayment = env.account(proof).await;
+    assert_eq!(pof.status, PaymentStatus::Cancelled);
+    let pool: FactoringPool = env.account(factoring_pool()).await;
+    assert_eq!(pool.receivables, 0);
+
+    // The merchant keeps what the pool paid; the LP gets back only what is left
+    assert_eq!(env.balance(env.merchant_usdc).await, payout);
+    assert_eq!((pool.unearned_discount, pool.discount_rate), (0, 0));
+    let lp_shares = LP_FUNDS - FACTORING_DEAD_SHARES;
+    env.withdraw_liquidity(lp_shares).await.unwrap();
+    assert_eq!(env.balance(env.lp_usdc).await, lp_shares * (LP_FUNDS - payout) / LP_FUNDS);
+    let buyer: BuyerAccount = env.account(buyer_account(&env.buyer.pubkey())).await;
+    assert_eq!((buyer.locked_amount, buyer.unlockable_amount), (0, BUYER_FUNDS));
+}
+
+#[tokio::test]
+async fn finished_accounts_can_be_closed_for_rent() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let (proof, old_intent, ix) = env.purchase_ix(PAYMENT, vec![]).await;
+    let admin = env.admin.insecure_clone();
+    let intent_ix = ed25519_ix(&env.buyer, &old_intent.to_slice());
+    env.send(&[intent_ix, ix], &[&admin]).await.unwrap();
+
+    let err = env.close_proof_of_payment(proof).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InvalidStatusTransition));
+
+    // Harvested but unclaimed yield keeps the PoF open
+    env.warp_slots(SLOTS_PER_YEAR).await;
+    env.harvest(&[proof]).await.unwrap();
+    let err = env.close_proof_of_payment(proof).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::UnclaimedEarnings));
+
+    env.claim(proof, PAYMENT).await.unwrap();
+    let admin_lamports = env.ctx.banks_client.get_balance(env.admin.pubkey()).await.unwrap();
+    env.close_proof_of_payment(proof).await.unwrap();
+    assert!(env.ctx.banks_client.get_account(proof).await.unwrap().is_none());
+    assert!(env.ctx.banks_client.get_balance(env.admin.pubkey()).await.unwrap() > admin_lamports);
+
+    let err = env.close_buyer_account().await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::AccountNotEmpty));
+
+    env.unstake(BUYER_FUNDS).await.unwrap();
+    env.close_buyer_account().await.unwrap();
+
+    // The reopened account starts back at nonce 0, but under a new account number,
+    // so the intent signed for the old account can't buy again
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let (_, intent, ix) = env.purchase_ix(PAYMENT, vec![]).await;
+    assert_eq!((intent.nonce, old_intent.nonce), (0, 0));
+    assert_eq!((intent.account_number, old_intent.account_number), (1, 0));
+    let replay_ix = ed25519_ix(&env.buyer, &old_intent.to_slice());
+    let err = env.send(&[replay_ix, ix.clone()], &[&admin]).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::InvalidPurchaseIntent));
+
+    let intent_ix = ed25519_ix(&env.buyer, &intent.to_slice());
+    env.send(&[intent_ix, ix], &[&admin]).await.unwrap();
+}
+
+#[tokio::test]
+async fn buyer_account_without_purchases_can_be_closed() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    env.unstake(BUYER_FUNDS).await.unwrap();
+    env.close_buyer_account().await.unwrap();
+    assert!(env.ctx.banks_client.get_account(buyer_account(&env.buyer.pubkey())).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn buyer_needs_merchant_consent_to_cancel() {
+    let mut env = Env::new().await;
+    env.stake(BUYER_FUNDS).await.unwrap();
+    let (buyer, merchant, admin) = (env.buyer.insecure_clone(), env.merchant.insecure_clone(), env.admin.insecure_clone());
+
+    let proof = env.purchase(PAYMENT / 2).await;
+    let err = env.cancel(proof, &buyer, None).await.unwrap_err();
+    assert_eq!(custom_error(err), u32::from(ErrorCode::Unauthorized));
+    env.cancel(proof, &buyer, Some(&merchant)).await.unwrap();
+    assert!(env.ctx.banks_client.get_account(proof).await.unwrap().is_none());
+
+    let proof = env.purchase(PAYMENT / 2).await;
+    env.cancel(proof, &admin, None).await.unwrap();
+    let buyer_account: BuyerAccount = env.account(buyer_account(&buyer.pubkey())).await;
+    assert_eq!((buyer_account.locked_amount, buyer_account.open_pofs), (0, 0));
+}
@@ -1,6 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import {
+  Ed25519Program,
   Keypair,
   PublicKey,
   SystemProgram,
@@ -14,6 +15,7 @@ import {
   getAccount,
   TOKEN_PROGRAM_ID,
   getAssociatedTokenAddress,
+  getOrCreateAssociatedTokenAccount,
   createInitializeAccountInstruction,
   getMinimumBalanceForRentExemptAccount,
 } from "@solana/spl-token";
@@ -35,7 +37,7 @@ describe("freelunch", () => {
   let usdcMint: PublicKey;
   let buyerUsdcAccount: PublicKey;
   let merchantUsdcAccount: PublicKey;
-  // let protocolUsdcAccount: PublicKey;
+  let protocolUsdcAccount: PublicKey;
   let protocolCollateralAccount: PublicKey;
 
   // PDAs
@@ -67,6 +69,39 @@ describe("freelunch", () => {
     await connection.confirmTransaction(sig);
   };
 
+  // Buyer-signed purchase intent, sent as an Ed25519 pre-instruction
+  const purchaseIntent = async (
+    amount: anchor.BN,
+    bufferBps: anchor.BN,
+    expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 600),
+    installments: { dueAt: anchor.BN; amount: anchor.BN }[] = []
+  ) => {
+    const buyerState = await program.account.buyerAccount.fetch(
+      buyerAccountPda
+    );
+    const nonce = buyerState.purchaseNonce;
+    const message = Buffer.concat([
+      Buffer.from("freelunch:purchase-intent"),
+      program.programId.toBuffer(),
+      buyer.publicKey.toBuffer(),
+      buyerState.accountNumber.toArrayLike(Buffer, "le", 8),
+      merchant.publicKey.toBuffer(),
+      amount.toArrayLike(Buffer, "le", 8),
+      bufferBps.toArrayLike(Buffer, "le", 8),
+      nonce.toArrayLike(Buffer, "le", 8),
+      expiry.toArrayLike(Buffer, "le", 8),
+      ...installments.flatMap((i) => [
+        i.dueAt.toArrayLike(Buffer, "le", 8),
+        i.amount.toArrayLike(Buffer, "le", 8),
+      ]),
+    ]);
+    const ix = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: buyer.secretKey,
+      message,
+    });
+    return { nonce, expiry, ix };
+  };
+
   before(async () => {
     await airdrop(admin.publicKey, 5);
     await airdrop(buyer.publicKey, 5);
@@ -162,6 +197,19 @@ describe("freelunch", () => {
       protocolCollateralAccount.toBase58()
     );
 
+    // Redeemed USDC lands here before it is paid out; it must be the vault's ATA
+    protocolUsdcAccount = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        admin,
+        usdcMint,
+        protocolVaultPda,
+        true
+      )
+    ).address;
+
+    console.log("Protocol USDC account:", protocolUsdcAccount.toBase58());
+
     [buyerAccountPda, buyerAccountBump] =
       await PublicKey.findProgramAddressSync(
         [Buffer.from("buyer"), buyer.publicKey.toBuffer()],
@@ -219,13 +267,36 @@ describe("freelunch", () => {
 
   it("Initialize Protocol Vault", async () => {
     // IDL: init => protocol_vault, admin, system_program
-    const txSig = await program.methods
-      .init()
-      .accounts({
-        admin: admin.publicKey,
-      })
-      .signers([admin])
-      .rpc();
+    const init = (usdcAccount: PublicKey) =>
+      program.methods
+        .init()
+        .accounts({
+          admin: admin.publicKey,
+          usdcMint: usdcMint,
+          protocolUsdcAccount: usdcAccount,
+          protocolCollateralAccount: protocolCollateralAccount,
+          solendProgram: solendProgram,
+          solendReserve: solendReserve,
+        })
+        .signers([admin])
+        .rpc();
+
+    // A vault-owned USDC account that isn't the vault's ATA could never settle swaps
+    const notAta = await createAccount(
+      connection,
+      admin,
+      usdcMint,
+      protocolVaultPda,
+      Keypair.generate()
+    );
+    try {
+      await init(notAta);
+      assert.fail("init should reject a non-ATA USDC account");
+    } catch (err) {
+      assert.equal(err.error?.errorCode?.code, "ConstraintAssociated");
+    }
+
+    const txSig = await init(protocolUsdcAccount);
 
     console.log("init tx:", txSig);
 
@@ -236,6 +307,8 @@ describe("freelunch", () => {
       vaultState.admin.equals(admin.publicKey),
       "Vault admin must match"
     );
+    assert.ok(vaultState.usdcAccount.equals(protocolUsdcAccount));
+    assert.ok(vaultState.collateralAccount.equals(protocolCollateralAccount));
     assert.equal(vaultState.totalStaked.toNumber(), 0);
     assert.equal(vaultState.bump, protocolVaultBump);
   });
@@ -314,14 +387,23 @@ describe("freelunch", () => {
     proofOfPaymentPda = pofPda;
     proofOfPaymentBump = pofBump;
 
+    const intent = await purchaseIntent(purchaseAmount, bufferBps);
+
     await program.methods
-      .createProofOfPayment(purchaseAmount, bufferBps)
+      .createProofOfPayment(
+        purchaseAmount,
+        bufferBps,
+        intent.nonce,
+        intent.expiry,
+        []
+      )
       .accounts({
         admin: admin.publicKey,
         buyerAccount: buyerAccountPda,
         merchant: merchant.publicKey,
         solendReserve: solendReserve,
       })
+      .preInstructions([intent.ix])
       .signers([admin])
       .rpc();
 
@@ -329,6 +411,7 @@ describe("freelunch", () => {
       proofOfPaymentPda
     );
     assert.equal(pofState.paymentAmount.toNumber(), 5_000_000);
+    assert.deepEqual(pofState.status, { pending: {} });
   });
 
   it("Fulfill Proof Of Payment (partial)", async () => {
@@ -336,10 +419,10 @@ describe("freelunch", () => {
     const payNow = new anchor.BN(3_000_000);
 
     await program.methods
-      .fulfillProofOfPayment(payNow)
+      .fulfillProofOfPayment(payNow, new anchor.BN(0))
       .accounts({
         protocolSigner: admin.publicKey,
-        protocolUsdcAccount: merchantUsdcAccount, // <--- Using protocolUsdcAccount now
+        protocolUsdcAccount: protocolUsdcAccount,
         merchantUsdcAccount: merchantUsdcAccount,
         proofOfPayment: proofOfPaymentPda,
         buyerAccount: buyerAccountPda,
@@ -355,30 +438,49 @@ describe("freelunch", () => {
       .rpc();
   });
 
-  it("Merchant Claim (partial)", async () => {
-    const claimAmount = new anchor.BN(1_000_000);
+  it("Merchant cannot claim ahead of yield", async () => {
+    // The partial fulfillment was paid out directly, so nothing is left to claim
+    const pofBefore = await program.account.proofOfFuturePayment.fetch(
+      proofOfPaymentPda
+    );
+    assert.equal(pofBefore.earnedUnclaimed.toNumber(), 0);
+    const usdcBefore = await getAccount(connection, merchantUsdcAccount);
 
-    await program.methods
-      .merchantClaim(claimAmount)
-      .accounts({
-        merchant: merchant.publicKey,
-        proofOfPayment: proofOfPaymentPda,
-        buyerAccount: buyerAccountPda,
-        protocolUsdcAccount: merchantUsdcAccount, // <--- The protocol's USDC
-        merchantUsdcAccount: merchantUsdcAccount,
-      })
-      .signers([merchant])
-      .rpc();
+    try {
+      await program.methods
+        .merchantClaim(new anchor.BN(1_000_000), new anchor.BN(0))
+        .accounts({
+          merchant: merchant.publicKey,
+          proofOfPayment: proofOfPaymentPda,
+          protocolUsdcAccount: protocolUsdcAccount,
+          merchantUsdcAccount: merchantUsdcAccount,
+        })
+        .signers([merchant])
+        .rpc();
+      assert.fail("claim without earned yield should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "NothingToClaim");
+    }
+
+    const usdcAfter = await getAccount(connection, merchantUsdcAccount);
+    assert.equal(usdcAfter.amount.toString(), usdcBefore.amount.toString());
+    const pofAfter = await program.account.proofOfFuturePayment.fetch(
+      proofOfPaymentPda
+    );
+    assert.equal(
+      pofAfter.amountFulfilled.toString(),
+      pofBefore.amountFulfilled.toString()
+    );
   });
 
   it("Fulfill final portion (complete PoF)", async () => {
     const payNow = new anchor.BN(2_000_000);
 
     await program.methods
-      .fulfillProofOfPayment(payNow)
+      .fulfillProofOfPayment(payNow, new anchor.BN(0))
       .accounts({
         protocolSigner: admin.publicKey,
-        protocolUsdcAccount: merchantUsdcAccount, // <--- again
+        protocolUsdcAccount: protocolUsdcAccount,
         merchantUsdcAccount: merchantUsdcAccount,
         proofOfPayment: proofOfPaymentPda,
         buyerAccount: buyerAccountPda,
@@ -401,7 +503,7 @@ describe("freelunch", () => {
       .unstake(withdrawAmount)
       .accounts({
         buyer: buyer.publicKey,
-        protocolUsdcAccount: merchantUsdcAccount,
+        protocolUsdcAccount: protocolUsdcAccount,
         buyerUsdcAccount: buyerUsdcAccount,
         protocolCollateralAccount: protocolCollateralAccount,
         solendProgram: solendProgram,
@@ -414,4 +516,950 @@ describe("freelunch", () => {
       .signers([buyer])
       .rpc();
   });
+
+  it("Cancel unfulfilled PoF (buyer with merchant consent)", async () => {
+    const merchantBefore = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    const [pofPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proof_of_payment"),
+        buyer.publicKey.toBuffer(),
+        merchant.publicKey.toBuffer(),
+        new anchor.BN(merchantBefore.paymentNumber).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    const intent = await purchaseIntent(
+      new anchor.BN(1_000_000),
+      new anchor.BN(500)
+    );
+
+    await program.methods
+      .createProofOfPayment(
+        new anchor.BN(1_000_000),
+        new anchor.BN(500),
+        intent.nonce,
+        intent.expiry,
+        []
+      )
+      .accounts({
+        admin: admin.publicKey,
+        buyerAccount: buyerAccountPda,
+        merchant: merchant.publicKey,
+        solendReserve: solendReserve,
+      })
+      .preInstructions([intent.ix])
+      .signers([admin])
+      .rpc();
+
+    const buyerBefore = await program.account.buyerAccount.fetch(
+      buyerAccountPda
+    );
+    const pofState = await program.account.proofOfFuturePayment.fetch(pofPda);
+
+    // The buyer can't walk away from a purchase on their own
+    try {
+      await program.methods
+        .cancelProofOfPayment()
+        .accounts({
+          authority: buyer.publicKey,
+          merchant: null,
+          proofOfPayment: pofPda,
+          rentReceiver: admin.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("buyer-only cancel should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+
+    await program.methods
+      .cancelProofOfPayment()
+      .accounts({
+        authority: buyer.publicKey,
+        merchant: merchant.publicKey,
+        proofOfPayment: pofPda,
+        rentReceiver: admin.publicKey,
+      })
+      .signers([buyer, merchant])
+      .rpc();
+
+    const buyerAfter = await program.account.buyerAccount.fetch(
+      buyerAccountPda
+    );
+    assert.equal(
+      buyerAfter.unlockableAmount.toNumber(),
+      buyerBefore.unlockableAmount.toNumber() +
+        pofState.lockedCollateral.toNumber()
+    );
+    assert.isNull(await connection.getAccountInfo(pofPda));
+  });
+
+  it("Dispute freezes claims until the arbiter refunds the buyer", async () => {
+    const merchantBefore = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    const [pofPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proof_of_payment"),
+        buyer.publicKey.toBuffer(),
+        merchant.publicKey.toBuffer(),
+        new anchor.BN(merchantBefore.paymentNumber).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    const intent = await purchaseIntent(
+      new anchor.BN(1_000_000),
+      new anchor.BN(500)
+    );
+
+    await program.methods
+      .createProofOfPayment(
+        new anchor.BN(1_000_000),
+        new anchor.BN(500),
+        intent.nonce,
+        intent.expiry,
+        []
+      )
+      .accounts({
+        admin: admin.publicKey,
+        buyerAccount: buyerAccountPda,
+        merchant: merchant.publicKey,
+        solendReserve: solendReserve,
+      })
+      .preInstructions([intent.ix])
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .openDispute()
+      .accounts({ buyer: buyer.publicKey, proofOfPayment: pofPda })
+      .signers([buyer])
+      .rpc();
+
+    try {
+      await program.methods
+        .merchantClaim(new anchor.BN(1_000_000), new anchor.BN(0))
+        .accounts({
+          merchant: merchant.publicKey,
+          proofOfPayment: pofPda,
+          protocolUsdcAccount: protocolUsdcAccount,
+          merchantUsdcAccount: merchantUsdcAccount,
+        })
+        .signers([merchant])
+        .rpc();
+      assert.fail("claim should be rejected while disputed");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "PaymentDisputed");
+    }
+
+    // The admin is the default arbiter
+    await program.methods
+      .resolveDispute({ refundBuyer: {} })
+      .accounts({ arbiter: admin.publicKey, proofOfPayment: pofPda, factoringPool: null })
+      .signers([admin])
+      .rpc();
+
+    const pofState = await program.account.proofOfFuturePayment.fetch(pofPda);
+    assert.deepEqual(pofState.status, { cancelled: {} });
+  });
+
+  it("Purchase intent cannot be replayed", async () => {
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+
+    const merchantBefore = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    const accounts = {
+      admin: admin.publicKey,
+      buyerAccount: buyerAccountPda,
+      merchant: merchant.publicKey,
+      solendReserve: solendReserve,
+    };
+
+    await program.methods
+      .createProofOfPayment(
+        amount,
+        bufferBps,
+        intent.nonce,
+        intent.expiry,
+        []
+      )
+      .accounts(accounts)
+      .preInstructions([intent.ix])
+      .signers([admin])
+      .rpc();
+
+    const merchantAfter = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    assert.equal(
+      merchantAfter.paymentNumber.toNumber(),
+      merchantBefore.paymentNumber.toNumber() + 1
+    );
+
+    try {
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts(accounts)
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc();
+      assert.fail("replayed intent should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidNonce");
+    }
+  });
+
+  it("Purchase without the buyer's signature is rejected", async () => {
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+
+    // Same intent, signed by the admin instead of the buyer
+    const forged = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: admin.secretKey,
+      message: intent.ix.data.subarray(16 + 32 + 64),
+    });
+
+    try {
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: merchant.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([forged])
+        .signers([admin])
+        .rpc();
+      assert.fail("forged intent should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidPurchaseIntent");
+    }
+  });
+
+  it("Invariant: per-buyer sums match the vault totals", async () => {
+    const vaultState = await program.account.protocolVault.fetch(
+      protocolVaultPda
+    );
+    const buyers = await program.account.buyerAccount.all();
+
+    const sum = (f: (b: any) => anchor.BN) =>
+      buyers.reduce((acc, b) => acc.add(f(b.account)), new anchor.BN(0));
+
+    assert.equal(
+      sum((b) => b.stakedAmount).toString(),
+      vaultState.totalStaked.toString()
+    );
+    assert.equal(
+      sum((b) => b.lockedAmount).toString(),
+      vaultState.totalLocked.toString()
+    );
+    assert.equal(
+      sum((b) => b.lockedAmount.add(b.unlockableAmount)).toString(),
+      vaultState.totalStaked.toString()
+    );
+
+    // Outstanding liabilities are exactly what open PoFs still owe
+    const pofs = await program.account.proofOfFuturePayment.all();
+    const owed = pofs
+      .filter(
+        (p) =>
+          "pending" in p.account.status ||
+          "partiallyPaid" in p.account.status ||
+          "disputed" in p.account.status
+      )
+      .reduce(
+        (acc, p) =>
+          acc.add(p.account.paymentAmount.sub(p.account.amountFulfilled)),
+        new anchor.BN(0)
+      );
+    assert.equal(owed.toString(), vaultState.pendingPayments.toString());
+  });
+
+  it("Solvency report", async () => {
+    const report = await program.methods.solvencyReport().view();
+    const vaultState = await program.account.protocolVault.fetch(
+      protocolVaultPda
+    );
+
+    assert.equal(
+      report.totalStaked.toString(),
+      vaultState.totalStaked.toString()
+    );
+    assert.equal(
+      report.totalLocked.toString(),
+      vaultState.totalLocked.toString()
+    );
+    assert.equal(
+      report.totalUnlocked.toString(),
+      vaultState.totalStaked.sub(vaultState.totalLocked).toString()
+    );
+    assert.equal(
+      report.pendingPayments.toString(),
+      vaultState.pendingPayments.toString()
+    );
+    assert.equal(
+      report.totalRewards.toString(),
+      vaultState.totalRewards.toString()
+    );
+  });
+
+  it("Merchant settlement preference", async () => {
+    // A settlement mint needs a pool that trades it
+    try {
+      await program.methods
+        .setSettlementPreference(Keypair.generate().publicKey)
+        .accounts({ merchant: merchant.publicKey, settlementPool: null })
+        .signers([merchant])
+        .rpc();
+      assert.fail("settlement mint without a pool should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InvalidSettlementPool");
+    }
+
+    await program.methods
+      .setSettlementPreference(null)
+      .accounts({ merchant: merchant.publicKey, settlementPool: null })
+      .signers([merchant])
+      .rpc();
+
+    const merchantState = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    assert.isNull(merchantState.settlementMint);
+    assert.isNull(merchantState.settlementPool);
+  });
+
+  it("Purchase with an installment plan", async () => {
+    const now = Math.floor(Date.now() / 1000);
+    const installments = [
+      { dueAt: new anchor.BN(now + 30 * 86_400), amount: new anchor.BN(400_000) },
+      { dueAt: new anchor.BN(now + 60 * 86_400), amount: new anchor.BN(600_000) },
+    ];
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(
+      amount,
+      bufferBps,
+      undefined,
+      installments
+    );
+
+    const merchantBefore = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    const [pofPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proof_of_payment"),
+        buyer.publicKey.toBuffer(),
+        merchant.publicKey.toBuffer(),
+        new anchor.BN(merchantBefore.paymentNumber).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    await program.methods
+      .createProofOfPayment(
+        amount,
+        bufferBps,
+        intent.nonce,
+        intent.expiry,
+        installments
+      )
+      .accounts({
+        admin: admin.publicKey,
+        buyerAccount: buyerAccountPda,
+        merchant: merchant.publicKey,
+        solendReserve: solendReserve,
+      })
+      .preInstructions([intent.ix])
+      .signers([admin])
+      .rpc();
+
+    const schedule = await program.methods
+      .installmentSchedule()
+      .accounts({ proofOfPayment: pofPda })
+      .view();
+    assert.equal(schedule.installments.length, 2);
+    assert.equal(schedule.currentInstallment, 0);
+    assert.equal(
+      schedule.nextDueAt.toNumber(),
+      installments[0].dueAt.toNumber()
+    );
+  });
+
+  it("APY safeguards", async () => {
+    const vaultState = await program.account.protocolVault.fetch(
+      protocolVaultPda
+    );
+    assert.equal(vaultState.minBufferBps.toNumber(), 500);
+    assert.isBelow(
+      vaultState.apyFloorBps.toNumber(),
+      vaultState.apyCapBps.toNumber()
+    );
+
+    // Only the admin can change the parameters
+    try {
+      await program.methods
+        .updateApyParams(
+          new anchor.BN(1),
+          new anchor.BN(10000),
+          false
+        )
+        .accounts({ admin: merchant.publicKey })
+        .signers([merchant])
+        .rpc();
+      assert.fail("non-admin should not update APY params");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+
+    // A buffer below the protocol minimum is rejected
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(100);
+    const intent = await purchaseIntent(amount, bufferBps);
+    try {
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: merchant.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc();
+      assert.fail("buffer below the minimum should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "BufferTooLow");
+    }
+  });
+
+  it("Emits events for the PoF lifecycle", async () => {
+    const eventsOf = async (signature: string) => {
+      await connection.confirmTransaction(signature, "confirmed");
+      const tx = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      return [...parser.parseLogs(tx.meta.logMessages)];
+    };
+
+    const merchantBefore = await program.account.merchantAccount.fetch(
+      merchantAccountPda
+    );
+    const [pofPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("proof_of_payment"),
+        buyer.publicKey.toBuffer(),
+        merchant.publicKey.toBuffer(),
+        new anchor.BN(merchantBefore.paymentNumber).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+    const created = await eventsOf(
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: merchant.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc()
+    );
+    const pofState = await program.account.proofOfFuturePayment.fetch(pofPda);
+
+    assert.equal(created.length, 1);
+    assert.equal(created[0].name, "proofOfPaymentCreated");
+    assert.isTrue(created[0].data.proofOfPayment.equals(pofPda));
+    assert.equal(
+      created[0].data.paymentNumber.toString(),
+      merchantBefore.paymentNumber.toString()
+    );
+    assert.equal(
+      created[0].data.lockedCollateral.toString(),
+      pofState.lockedCollateral.toString()
+    );
+    assert.isAbove(created[0].data.apyBps.toNumber(), 0);
+
+    const cancelled = await eventsOf(
+      await program.methods
+        .cancelProofOfPayment()
+        .accounts({
+          authority: buyer.publicKey,
+          merchant: merchant.publicKey,
+          proofOfPayment: pofPda,
+          rentReceiver: admin.publicKey,
+        })
+        .signers([buyer, merchant])
+        .rpc()
+    );
+
+    assert.equal(cancelled.length, 1);
+    assert.equal(cancelled[0].name, "paymentStatusChanged");
+    assert.property(cancelled[0].data.from, "pending");
+    assert.property(cancelled[0].data.to, "cancelled");
+    assert.equal(cancelled[0].data.amountFulfilled.toNumber(), 0);
+  });
+
+  it("Rejects substituted accounts", async () => {
+    const expectError = async (tx: Promise<string>, code: string) => {
+      try {
+        await tx;
+        assert.fail(`expected ${code}`);
+      } catch (err) {
+        assert.equal(err.error.errorCode.code, code);
+      }
+    };
+
+    const stakeAccounts = {
+      buyer: buyer.publicKey,
+      buyerUsdcAccount: buyerUsdcAccount,
+      solendProgram: solendProgram,
+      solendReserve: solendReserve,
+      reserveLiquiditySupply: reserveLiquiditySupply,
+      reserveCollateralMint: reserveCollateralMint,
+      lendingMarket: lendingMarket,
+      lendingMarketAuthority: lendingMarketAuthority,
+      protocolCollateralAccount: protocolCollateralAccount,
+    };
+    const stake = (overrides: object) =>
+      program.methods
+        .stake(new anchor.BN(1_000_000))
+        .accounts({ ...stakeAccounts, ...overrides })
+        .signers([buyer])
+        .rpc();
+
+    // Someone else's USDC account as the source
+    await expectError(
+      stake({ buyerUsdcAccount: merchantUsdcAccount }),
+      "ConstraintTokenOwner"
+    );
+    // A reserve other than the pinned one
+    await expectError(
+      stake({ solendReserve: Keypair.generate().publicKey }),
+      "InvalidReserve"
+    );
+    // Collateral minted into any account other than the pinned one
+    await expectError(
+      stake({ protocolCollateralAccount: protocolUsdcAccount }),
+      "InvalidProtocolAccount"
+    );
+
+    const fulfillAccounts = {
+      protocolSigner: admin.publicKey,
+      protocolUsdcAccount: protocolUsdcAccount,
+      merchantUsdcAccount: merchantUsdcAccount,
+      proofOfPayment: proofOfPaymentPda,
+      buyerAccount: buyerAccountPda,
+      solendProgram: solendProgram,
+      solendReserve: solendReserve,
+      reserveLiquiditySupply: reserveLiquiditySupply,
+      reserveCollateralMint: reserveCollateralMint,
+      lendingMarket: lendingMarket,
+      lendingMarketAuthority: lendingMarketAuthority,
+      protocolCollateralAccount: protocolCollateralAccount,
+    };
+    const fulfill = (overrides: object, signer: Keypair) =>
+      program.methods
+        .fulfillProofOfPayment(new anchor.BN(1), new anchor.BN(0))
+        .accounts({ ...fulfillAccounts, ...overrides })
+        .signers([signer])
+        .rpc();
+
+    // Only the protocol admin may fulfill
+    await expectError(
+      fulfill({ protocolSigner: merchant.publicKey }, merchant),
+      "Unauthorized"
+    );
+    // Payout to an account the PoF's merchant doesn't own
+    await expectError(
+      fulfill({ merchantUsdcAccount: buyerUsdcAccount }, admin),
+      "ConstraintTokenOwner"
+    );
+    // Paying out of an account the vault doesn't control
+    await expectError(
+      fulfill({ protocolUsdcAccount: merchantUsdcAccount }, admin),
+      "InvalidProtocolAccount"
+    );
+    // A substituted lending market
+    await expectError(
+      fulfill({ lendingMarket: Keypair.generate().publicKey }, admin),
+      "InvalidReserve"
+    );
+
+    const claim = (overrides: object) =>
+      program.methods
+        .merchantClaim(new anchor.BN(1), new anchor.BN(0))
+        .accounts({
+          merchant: merchant.publicKey,
+          proofOfPayment: proofOfPaymentPda,
+          protocolUsdcAccount: protocolUsdcAccount,
+          merchantUsdcAccount: merchantUsdcAccount,
+          ...overrides,
+        })
+        .signers([merchant])
+        .rpc();
+
+    // Draining the merchant's own account into itself, or paying a stranger
+    await expectError(
+      claim({ protocolUsdcAccount: merchantUsdcAccount }),
+      "InvalidProtocolAccount"
+    );
+    await expectError(
+      claim({ merchantUsdcAccount: buyerUsdcAccount }),
+      "ConstraintTokenOwner"
+    );
+
+    // A purchase naming a merchant other than the merchant account's owner
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+    await expectError(
+      program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: buyer.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc(),
+      "InvalidMerchant"
+    );
+  });
+
+  it("Admin governance: rotation, pause and parameters", async () => {
+    const newAdmin = Keypair.generate();
+    await airdrop(newAdmin.publicKey);
+
+    // Rotation only takes effect once the proposed admin accepts
+    await program.methods
+      .setAdmin(newAdmin.publicKey)
+      .accounts({ admin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+    let vaultState = await program.account.protocolVault.fetch(
+      protocolVaultPda
+    );
+    assert.ok(vaultState.admin.equals(admin.publicKey));
+    assert.ok(vaultState.pendingAdmin.equals(newAdmin.publicKey));
+
+    try {
+      await program.methods
+        .acceptAdmin()
+        .accounts({ newAdmin: merchant.publicKey })
+        .signers([merchant])
+        .rpc();
+      assert.fail("only the proposed admin can accept");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+
+    await program.methods
+      .acceptAdmin()
+      .accounts({ newAdmin: newAdmin.publicKey })
+      .signers([newAdmin])
+      .rpc();
+    vaultState = await program.account.protocolVault.fetch(protocolVaultPda);
+    assert.ok(vaultState.admin.equals(newAdmin.publicKey));
+    assert.isNull(vaultState.pendingAdmin);
+
+    // Hand the role back so the remaining tests keep their admin
+    await program.methods
+      .setAdmin(admin.publicKey)
+      .accounts({ admin: newAdmin.publicKey })
+      .signers([newAdmin])
+      .rpc();
+    await program.methods
+      .acceptAdmin()
+      .accounts({ newAdmin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+
+    // Parameters are admin-gated and validated
+    try {
+      await program.methods
+        .updateParams(new anchor.BN(0), new anchor.BN(1), 0, 0)
+        .accounts({ admin: merchant.publicKey })
+        .signers([merchant])
+        .rpc();
+      assert.fail("non-admin should not update params");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+    await program.methods
+      .updateParams(new anchor.BN(500), new anchor.BN(2_000_000), 50, 1000)
+      .accounts({ admin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+    vaultState = await program.account.protocolVault.fetch(protocolVaultPda);
+    assert.equal(vaultState.maxPurchaseAmount.toNumber(), 2_000_000);
+
+    const amount = new anchor.BN(3_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+    try {
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: merchant.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc();
+      assert.fail("purchase above the maximum should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "PurchaseTooLarge");
+    }
+
+    // Pausing stops new stake but not unstaking unlocked funds
+    await program.methods
+      .setPaused(true)
+      .accounts({ admin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+    try {
+      await program.methods
+        .stake(new anchor.BN(1_000_000))
+        .accounts({
+          buyer: buyer.publicKey,
+          buyerUsdcAccount: buyerUsdcAccount,
+          solendProgram: solendProgram,
+          solendReserve: solendReserve,
+          reserveLiquiditySupply: reserveLiquiditySupply,
+          reserveCollateralMint: reserveCollateralMint,
+          lendingMarket: lendingMarket,
+          lendingMarketAuthority: lendingMarketAuthority,
+          protocolCollateralAccount: protocolCollateralAccount,
+        })
+        .signers([buyer])
+        .rpc();
+      assert.fail("stake should be rejected while paused");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ProtocolPaused");
+    }
+    await program.methods
+      .unstake(new anchor.BN(1_000_000))
+      .accounts({
+        buyer: buyer.publicKey,
+        protocolUsdcAccount: protocolUsdcAccount,
+        buyerUsdcAccount: buyerUsdcAccount,
+        protocolCollateralAccount: protocolCollateralAccount,
+        solendProgram: solendProgram,
+        solendReserve: solendReserve,
+        reserveLiquiditySupply: reserveLiquiditySupply,
+        reserveCollateralMint: reserveCollateralMint,
+        lendingMarket: lendingMarket,
+        lendingMarketAuthority: lendingMarketAuthority,
+      })
+      .signers([buyer])
+      .rpc();
+
+    await program.methods
+      .setPaused(false)
+      .accounts({ admin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+    await program.methods
+      .updateParams(new anchor.BN(500), new anchor.BN(10_000_000_000), 50, 1000)
+      .accounts({ admin: admin.publicKey })
+      .signers([admin])
+      .rpc();
+  });
+
+  it("Protocol fees accumulate in the treasury", async () => {
+    const [treasuryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      program.programId
+    );
+    const treasury = await getAccount(connection, treasuryPda);
+    assert.ok(treasury.owner.equals(protocolVaultPda));
+    assert.ok(treasury.mint.equals(usdcMint));
+
+    const vaultState = await program.account.protocolVault.fetch(
+      protocolVaultPda
+    );
+    assert.equal(vaultState.protocolFeeBps, 1000);
+    assert.equal(
+      vaultState.totalProtocolFees.toString(),
+      treasury.amount.toString()
+    );
+
+    try {
+      await program.methods
+        .withdrawProtocolFees(new anchor.BN(1))
+        .accounts({
+          admin: merchant.publicKey,
+          destination: merchantUsdcAccount,
+        })
+        .signers([merchant])
+        .rpc();
+      assert.fail("non-admin should not withdraw fees");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+
+    try {
+      await program.methods
+        .withdrawProtocolFees(
+          new anchor.BN(treasury.amount.toString()).addn(1)
+        )
+        .accounts({
+          admin: admin.publicKey,
+          destination: merchantUsdcAccount,
+        })
+        .signers([admin])
+        .rpc();
+      assert.fail("cannot withdraw more than the treasury holds");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "InsufficientFunds");
+    }
+  });
+
+  it("Buyer credit limit and summary", async () => {
+    const buyerState = await program.account.buyerAccount.fetch(
+      buyerAccountPda
+    );
+    const summary = await program.methods
+      .buyerSummary()
+      .accounts({ buyerAccount: buyerAccountPda, solendReserve: solendReserve })
+      .view();
+
+    assert.equal(
+      summary.stakedAmount.toString(),
+      buyerState.stakedAmount.toString()
+    );
+    assert.equal(
+      summary.lockedAmount.toString(),
+      buyerState.lockedAmount.toString()
+    );
+    assert.equal(
+      summary.unlockableAmount.toString(),
+      buyerState.unlockableAmount.toString()
+    );
+    assert.equal(summary.openPofs, buyerState.openPofs);
+    assert.equal(
+      summary.outstandingAmount.toString(),
+      buyerState.outstandingAmount.toString()
+    );
+
+    // Open PoFs and outstanding amounts match the buyer's PoFs
+    const open = (await program.account.proofOfFuturePayment.all()).filter(
+      (p) =>
+        p.account.buyer.equals(buyer.publicKey) &&
+        !("completed" in p.account.status) &&
+        !("cancelled" in p.account.status)
+    );
+    assert.equal(buyerState.openPofs, open.length);
+
+    // A purchase beyond the credit limit is rejected
+    await program.methods
+      .setCreditLimit(buyerState.outstandingAmount)
+      .accounts({ admin: admin.publicKey, buyerAccount: buyerAccountPda })
+      .signers([admin])
+      .rpc();
+
+    const amount = new anchor.BN(1_000_000);
+    const bufferBps = new anchor.BN(500);
+    const intent = await purchaseIntent(amount, bufferBps);
+    try {
+      await program.methods
+        .createProofOfPayment(
+          amount,
+          bufferBps,
+          intent.nonce,
+          intent.expiry,
+          []
+        )
+        .accounts({
+          admin: admin.publicKey,
+          buyerAccount: buyerAccountPda,
+          merchant: merchant.publicKey,
+          solendReserve: solendReserve,
+        })
+        .preInstructions([intent.ix])
+        .signers([admin])
+        .rpc();
+      assert.fail("purchase beyond the credit limit should be rejected");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "ExposureLimitExceeded");
+    }
+
+    try {
+      await program.methods
+        .setCreditLimit(new anchor.BN(0))
+        .accounts({ admin: buyer.publicKey, buyerAccount: buyerAccountPda })
+        .signers([buyer])
+        .rpc();
+      assert.fail("buyers cannot change their own limit");
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, "Unauthorized");
+    }
+
+    await program.methods
+      .setCreditLimit(buyerState.maxExposure)
+      .accounts({ admin: admin.publicKey, buyerAccount: buyerAccountPda })
+      .signers([admin])
+      .rpc();
+  });
 });
@@ -1,4 +1,17 @@
 use anchor_lang::prelude::*;
 
 #[constant]
-pub const SEED: &str = "anchor";
\ No newline at end of file
+pub const SEED: &str = "anchor";
+
+// Hard limits the house's configurable settings must stay within
+#[constant]
+pub const MIN_BET: u64 = 10_000_000; // 0.01 SOL
+
+#[constant]
+pub const MIN_ROLL: u8 = 2;
+
+#[constant]
+pub const MAX_ROLL: u8 = 96;
+
+#[constant]
+pub const MAX_REFUND_TIMEOUT: u64 = 216_000; // About a day of slots
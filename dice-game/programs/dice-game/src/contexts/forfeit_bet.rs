@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{state::{Bet, Bankroll}, errors::DiceError};
+
+#[derive(Accounts)]
+pub struct ForfeitBet<'info> {
+    #[account(mut)]
+    ///CHECK: Only receives the bet's rent, pinned by the bet
+    pub player: UncheckedAccount<'info>,
+    pub house: SystemAccount<'info>,
+    #[account(
+        seeds = [b"vault", house.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        mut,
+        has_one = player,
+        close = player,
+        seeds = [b"bet", vault.key().as_ref(), bet.seed.to_le_bytes().as_ref()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+}
+
+impl<'info> ForfeitBet<'info> {
+    /// Permissionless: a player who sits on their secret for the refund timeout after
+    /// the resolver signs loses the stake, so holding back a losing reveal gains nothing.
+    pub fn forfeit_bet(&mut self) -> Result<()> {
+        require!(self.bet.signed(), DiceError::BetNotSigned);
+        require!(self.bet.timed_out(self.bet.signed_slot, Clock::get()?.slot)?, DiceError::TimeoutNotReached);
+        // The stake is already in the vault; only the reserved payout is freed
+        self.bankroll.release(self.bet.payout()?)
+    }
+}
@@ -1,23 +1,60 @@
 use anchor_lang::{prelude::*, system_program::{Transfer, transfer}};
 
+use crate::{state::{Bankroll, HouseConfig, HouseSettings}, errors::DiceError};
+
 #[derive(Accounts)]
 pub struct Initialize<'info>{
   #[account(mut)]
-  pub user: Signer<'info>, // house?
+  pub house: Signer<'info>,
 
   #[account(
       mut,
-      seeds = [b"vault", user.key().as_ref()],
+      seeds = [b"vault", house.key().as_ref()],
       bump
   )]
   pub vault: SystemAccount<'info>,
+
+  #[account(
+      init,
+      payer = house,
+      space = Bankroll::INIT_SPACE + 8,
+      seeds = [b"bankroll", house.key().as_ref()],
+      bump
+  )]
+  pub bankroll: Account<'info, Bankroll>,
+
+  #[account(
+      init,
+      payer = house,
+      space = HouseConfig::INIT_SPACE + 8,
+      seeds = [b"config", house.key().as_ref()],
+      bump
+  )]
+  pub config: Account<'info, HouseConfig>,
   pub system_program: Program<'info, System>
 }
 
 impl<'info> Initialize<'info>{
-  pub fn init(&mut self, amount:u64) -> Result<()>{
+  pub fn init(&mut self, amount:u64, max_payout_bps: u16, settings: HouseSettings, bumps: &InitializeBumps) -> Result<()>{
+    require!(max_payout_bps > 0 && max_payout_bps <= 10000, DiceError::InvalidParameter);
+    settings.validate()?;
+
+    self.config.set_inner(HouseConfig {
+      house: self.house.key(),
+      settings,
+      paused: false,
+      bump: bumps.config,
+    });
+
+    self.bankroll.set_inner(Bankroll {
+      house: self.house.key(),
+      max_payout_bps,
+      reserved: 0,
+      bump: bumps.bankroll,
+    });
+
     let cpi_accounts = Transfer{
-      from: self.user.to_account_info(),
+      from: self.house.to_account_info(),
       to: self.vault.to_account_info(),
     };
     let cpi_program = self.system_program.to_account_info();
@@ -25,4 +62,4 @@ impl<'info> Initialize<'info>{
     transfer(cpi_ctx, amount)?;
     Ok(())
   }
-}
\ No newline at end of file
+}
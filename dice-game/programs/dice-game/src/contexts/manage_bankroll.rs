@@ -0,0 +1,56 @@
+use anchor_lang::{prelude::*, system_program::{Transfer, transfer}};
+
+use crate::{state::Bankroll, errors::DiceError};
+
+#[derive(Accounts)]
+pub struct ManageBankroll<'info> {
+    #[account(mut)]
+    pub house: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", house.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> ManageBankroll<'info> {
+    pub fn deposit_bankroll(&mut self, amount: u64) -> Result<()> {
+        let accounts = Transfer {
+            from: self.house.to_account_info(),
+            to: self.vault.to_account_info()
+        };
+        let ctx = CpiContext::new(self.system_program.to_account_info(), accounts);
+        transfer(ctx, amount)
+    }
+
+    /// The house can take out anything not reserved for unresolved bets, as long as
+    /// the vault stays rent exempt.
+    pub fn withdraw_bankroll(&mut self, amount: u64, bumps: &ManageBankrollBumps) -> Result<()> {
+        let keep = self.bankroll.reserved
+            .checked_add(Rent::get()?.minimum_balance(0)).ok_or(DiceError::Overflow)?;
+        let withdrawable = self.vault.lamports().saturating_sub(keep);
+        require!(amount <= withdrawable, DiceError::InsufficientBankroll);
+
+        let accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.house.to_account_info()
+        };
+
+        let seeds = [b"vault", &self.house.key().to_bytes()[..], &[bumps.vault]];
+        let signer_seeds = &[&seeds[..]][..];
+
+        let ctx = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            accounts,
+            signer_seeds
+        );
+        transfer(ctx, amount)
+    }
+}
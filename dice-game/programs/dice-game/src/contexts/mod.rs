@@ -1,9 +1,21 @@
+pub mod forfeit_bet;
 pub mod init;
+pub mod manage_bankroll;
 pub mod place_bet;
 pub mod refund_bet;
 pub mod resolve_bet;
+pub mod resolve_bets_batch;
+pub mod reveal_bet;
+pub mod sign_bet;
+pub mod update_config;
 
+pub use forfeit_bet::*;
 pub use init::*;
+pub use manage_bankroll::*;
 pub use place_bet::*;
 pub use refund_bet::*;
 pub use resolve_bet::*;
+pub use resolve_bets_batch::*;
+pub use reveal_bet::*;
+pub use sign_bet::*;
+pub use update_config::*;
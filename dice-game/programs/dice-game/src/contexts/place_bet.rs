@@ -1,6 +1,6 @@
 use anchor_lang::{prelude::*, system_program::{transfer, Transfer}};
 
-use crate::state::Bet;
+use crate::{state::{Bet, Bankroll, HouseConfig}, errors::DiceError};
 
 #[derive(Accounts)]
 #[instruction(seed: u128)]
@@ -14,6 +14,18 @@ pub struct PlaceBet<'info> {
         bump,
     )]
     pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        seeds = [b"config", house.key().as_ref()],
+        bump = config.bump,
+        constraint = !config.paused @ DiceError::HousePaused
+    )]
+    pub config: Account<'info, HouseConfig>,
     #[account(
         init,
         payer = player,
@@ -32,15 +44,23 @@ impl<'info> PlaceBet<'info>{
     seed: u128,
     roll: u8,
     amount: u64,
+    commitment: [u8; 32],
     bumps: &PlaceBetBumps,
   )-> Result<()>{
-    &self.bet.set_inner(Bet {
+    self.config.settings.check_bet(roll, amount, &commitment)?;
+
+    self.bet.set_inner(Bet {
       player: self.player.key(),
       seed,
       slot:  Clock::get()?.slot,
       roll,
       amount,
-      bump: bumps.bet
+      bump: bumps.bet,
+      house_edge: self.config.settings.house_edge,
+      commitment,
+      house_hash: [0; 32],
+      signed_slot: 0,
+      refund_timeout: self.config.settings.refund_timeout,
     });
     Ok(())
   }
@@ -52,8 +72,15 @@ impl<'info> PlaceBet<'info>{
       to: self.vault.to_account_info(),
     };
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    transfer(cpi_ctx, amount);
+    transfer(cpi_ctx, amount)?;
 
     Ok(())
   }
+
+  /// Holds back the bet's potential payout, which may be at most `max_payout_bps`
+  /// of the vault not already reserved. Runs after `deposit`, so the stake counts.
+  pub fn reserve_payout(&mut self) -> Result<()> {
+    let payout = self.bet.payout()?;
+    self.bankroll.reserve(payout, self.vault.lamports())
+  }
 }
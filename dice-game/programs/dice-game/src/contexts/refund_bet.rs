@@ -1,9 +1,8 @@
 use anchor_lang::{prelude::*, system_program::{transfer, Transfer}};
 
-use crate::state::Bet;
+use crate::{state::{Bet, Bankroll}, errors::DiceError};
 
 #[derive(Accounts)]
-#[instruction(seed: u128)]
 pub struct RefundBet<'info> {
     #[account(mut)]
     pub player: Signer<'info>,
@@ -16,8 +15,15 @@ pub struct RefundBet<'info> {
     pub vault: SystemAccount<'info>,
     #[account(
         mut,
-        seeds = [b"bet", vault.key().as_ref(), seed.to_le_bytes().as_ref()],
-        bump,
+        seeds = [b"bankroll", casino.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        mut,
+        has_one = player,
+        seeds = [b"bet", vault.key().as_ref(), bet.seed.to_le_bytes().as_ref()],
+        bump = bet.bump,
         close = player,
     )]
 
@@ -26,10 +32,16 @@ pub struct RefundBet<'info> {
 }
 
 impl<'info> RefundBet<'info>{
+  /// Returns the stake of a bet the house left unresolved past the refund timeout.
   pub fn refund(
     &mut self,
     bumps: &RefundBetBumps,
   )-> Result<()>{
+    // Once signed, the player could work out a commit-reveal roll and back out of a loss
+    require!(!self.bet.signed(), DiceError::BetAlreadySigned);
+    require!(self.bet.timed_out(self.bet.slot, Clock::get()?.slot)?, DiceError::TimeoutNotReached);
+    self.bankroll.release(self.bet.payout()?)?;
+
     let cpi_program = self.system_program.to_account_info();
     let cpi_accounts = Transfer {
       from: self.vault.to_account_info(),
@@ -41,7 +53,7 @@ impl<'info> RefundBet<'info>{
 
     let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
 
-    transfer(cpi_ctx, self.vault.lamports())?;
-    Ok({})
+    transfer(cpi_ctx, self.bet.amount)?;
+    Ok(())
   }
-}
\ No newline at end of file
+}
@@ -2,15 +2,66 @@ use anchor_lang::{prelude::*, system_program::{Transfer, transfer}};
 use anchor_instruction_sysvar::Ed25519InstructionSignatures;
 use solana_program::{sysvar::instructions::load_instruction_at_checked, ed25519_program, hash::hash};
 
-use crate::{state::Bet, errors::DiceError};
+use crate::{state::{Bet, Bankroll, HouseConfig}, errors::DiceError};
 
+/// Checks that the transaction's first instruction is an Ed25519 verification of
+/// `sig` by `signer` over `message`.
+pub fn verify_ed25519(instruction_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8], sig: &[u8]) -> Result<()> {
+    verify_ed25519_batch(instruction_sysvar, signer, &[message], &[sig])
+}
+
+/// Like `verify_ed25519`, for an instruction carrying exactly one signature by
+/// `signer` per message, in the same order.
+pub fn verify_ed25519_batch(instruction_sysvar: &AccountInfo, signer: &Pubkey, messages: &[&[u8]], sigs: &[&[u8]]) -> Result<()> {
+    // Get the Ed25519 signature instruction 
+    let ix = load_instruction_at_checked(
+        0, 
+        instruction_sysvar
+    )?;
+    // Make sure the instruction is addressed to the ed25519 program
+    require_keys_eq!(ix.program_id, ed25519_program::ID, DiceError::Ed25519Program);
+    // Make sure there are no accounts present
+    require_eq!(ix.accounts.len(), 0, DiceError::Ed25519Accounts);
+    
+    let signatures = Ed25519InstructionSignatures::unpack(&ix.data)?.0;
 
-pub const HOUSE_EDGE: u16 = 150; // 1.5% House edge
+    require_eq!(signatures.len(), messages.len(), DiceError::Ed25519DataLength);
+    require_eq!(sigs.len(), messages.len(), DiceError::Ed25519DataLength);
+
+    for ((signature, message), sig) in signatures.iter().zip(messages).zip(sigs) {
+        // Make sure all the data is present to verify the signature
+        require!(signature.is_verifiable, DiceError::Ed25519Header);
+        
+        // Ensure public keys match
+        require_keys_eq!(signature.public_key.ok_or(DiceError::Ed25519Pubkey)?, *signer, DiceError::Ed25519Pubkey);
+
+        // Ensure signatures match
+        require!(&signature.signature.ok_or(DiceError::Ed25519Signature)?.eq(sig), DiceError::Ed25519Signature);
+
+        // Ensure messages match
+        require!(&signature.message.as_ref().ok_or(DiceError::Ed25519Signature)?.eq(message), DiceError::Ed25519Signature);
+    }
+
+    Ok(())
+}
+
+/// Maps 32 bytes of entropy to a roll from 1 to 100.
+pub fn roll_from_hash(hash: &[u8; 32]) -> u8 {
+    let mut hash_16: [u8;16] = [0;16];
+    hash_16.copy_from_slice(&hash[0..16]);
+    let lower = u128::from_le_bytes(hash_16);
+    hash_16.copy_from_slice(&hash[16..32]);
+    let upper = u128::from_le_bytes(hash_16);
+    
+    lower
+        .wrapping_add(upper)
+        .wrapping_rem(100) as u8 + 1
+}
 
 #[derive(Accounts)]
 pub struct ResolveBet<'info> {
-    #[account(mut)]
-    pub house: Signer<'info>,
+    pub resolver: Signer<'info>,
+    pub house: SystemAccount<'info>,
     #[account(
         mut
     )]
@@ -22,9 +73,23 @@ pub struct ResolveBet<'info> {
         bump
     )]
     pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        seeds = [b"config", house.key().as_ref()],
+        bump = config.bump,
+        constraint = config.settings.resolver == resolver.key() @ DiceError::InvalidResolver
+    )]
+    pub config: Account<'info, HouseConfig>,
     #[account(
         mut,
         close = player,
+        // The resolver is no longer necessarily the house, so pin the winnings to the bettor
+        has_one = player,
         seeds = [b"bet", vault.key().as_ref(), bet.seed.to_le_bytes().as_ref()],
         bump = bet.bump
     )]
@@ -40,55 +105,20 @@ pub struct ResolveBet<'info> {
 impl<'info> ResolveBet<'info> {
 
     pub fn verify_ed25519_signature(&mut self, sig: &[u8]) -> Result<()> {
-        // Get the Ed25519 signature instruction 
-        let ix = load_instruction_at_checked(
-            0, 
-            &self.instruction_sysvar.to_account_info()
-        )?;
-        // Make sure the instruction is addressed to the ed25519 program
-        require_keys_eq!(ix.program_id, ed25519_program::ID, DiceError::Ed25519Program);
-        // Make sure there are no accounts present
-        require_eq!(ix.accounts.len(), 0, DiceError::Ed25519Accounts);
-        
-        let signatures = Ed25519InstructionSignatures::unpack(&ix.data)?.0;
-
-        require_eq!(signatures.len(), 1, DiceError::Ed25519DataLength);
-        let signature = &signatures[0];
-
-        // Make sure all the data is present to verify the signature
-        require!(signature.is_verifiable, DiceError::Ed25519Header);
-        
-        // Ensure public keys match
-        require_keys_eq!(signature.public_key.ok_or(DiceError::Ed25519Pubkey)?, self.house.key(), DiceError::Ed25519Pubkey);
-
-        // Ensure signatures match
-        require!(&signature.signature.ok_or(DiceError::Ed25519Signature)?.eq(sig), DiceError::Ed25519Signature);
-
-        // Ensure messages match
-        require!(&signature.message.as_ref().ok_or(DiceError::Ed25519Signature)?.eq(&self.bet.to_slice()), DiceError::Ed25519Signature);
-
-        Ok(())
+        verify_ed25519(&self.instruction_sysvar, &self.resolver.key(), &self.bet.to_slice(), sig)
     }
 
     pub fn resolve_bet(&mut self, bumps: &ResolveBetBumps, sig: &[u8]) -> Result<()> {
-        let hash = hash(sig).to_bytes();
-        let mut hash_16: [u8;16] = [0;16];
-        hash_16.copy_from_slice(&hash[0..16]);
-        let lower = u128::from_le_bytes(hash_16);
-        hash_16.copy_from_slice(&hash[16..32]);
-        let upper = u128::from_le_bytes(hash_16);
-        
-        let roll = lower
-            .wrapping_add(upper)
-            .wrapping_rem(100) as u8 + 1;
+        // Commit-reveal bets go through sign_bet and reveal_bet instead
+        require!(!self.bet.commit_reveal(), DiceError::WrongRandomnessMode);
 
-        if self.bet.roll > roll {
+        let roll = roll_from_hash(&hash(sig).to_bytes());
 
-            // Payout minus house edge
-            let payout = (self.bet.amount as u128)
-            .checked_mul(10000 - HOUSE_EDGE as u128).ok_or(DiceError::Overflow)?
-            .checked_div(self.bet.roll as u128 - 1).ok_or(DiceError::Overflow)?
-            .checked_div(100).ok_or(DiceError::Overflow)? as u64;
+        // Payout minus the bet's house edge, reserved since it was placed
+        let payout = self.bet.payout()?;
+        self.bankroll.release(payout)?;
+
+        if self.bet.roll > roll {
 
             let accounts = Transfer {
                 from: self.vault.to_account_info(),
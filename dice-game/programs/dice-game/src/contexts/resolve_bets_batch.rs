@@ -0,0 +1,102 @@
+use anchor_lang::{prelude::*, system_program::{Transfer, transfer}};
+use solana_program::hash::hash;
+
+use crate::{state::{Bet, Bankroll, HouseConfig}, errors::DiceError, contexts::{verify_ed25519_batch, roll_from_hash}};
+
+#[derive(Accounts)]
+pub struct ResolveBetsBatch<'info> {
+    pub resolver: Signer<'info>,
+    pub house: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", house.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        seeds = [b"config", house.key().as_ref()],
+        bump = config.bump,
+        constraint = config.settings.resolver == resolver.key() @ DiceError::InvalidResolver
+    )]
+    pub config: Account<'info, HouseConfig>,
+    #[account(
+        address = solana_program::sysvar::instructions::ID
+    )]
+    /// CHECK: This is safe
+    pub instruction_sysvar: AccountInfo<'info>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> ResolveBetsBatch<'info> {
+    /// Resolves one bet per signature. `remaining_accounts` holds a writable
+    /// (bet, player) pair for each, in the order the Ed25519 instruction signs them.
+    pub fn resolve_bets_batch(
+        &mut self,
+        sigs: &[Vec<u8>],
+        remaining_accounts: &'info [AccountInfo<'info>],
+        bumps: &ResolveBetsBatchBumps,
+    ) -> Result<()> {
+        require!(
+            !sigs.is_empty() && remaining_accounts.len() == sigs.len() * 2,
+            DiceError::InvalidBetAccounts
+        );
+
+        let bets = remaining_accounts
+            .chunks(2)
+            .map(|pair| Account::<Bet>::try_from(&pair[0]))
+            .collect::<Result<Vec<_>>>()?;
+
+        let messages = bets.iter().map(|bet| bet.to_slice()).collect::<Vec<_>>();
+        verify_ed25519_batch(
+            &self.instruction_sysvar,
+            &self.resolver.key(),
+            &messages.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            &sigs.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        )?;
+
+        for ((bet, pair), sig) in bets.iter().zip(remaining_accounts.chunks(2)).zip(sigs) {
+            let player = &pair[1];
+            // A bet listed twice was already closed by its first settlement
+            require!(pair[0].lamports() > 0, DiceError::InvalidBetAccounts);
+            // Same checks the bet's seeds and has_one constraints make in resolve_bet
+            let bet_key = Pubkey::create_program_address(
+                &[b"bet", self.vault.key().as_ref(), bet.seed.to_le_bytes().as_ref(), &[bet.bump]],
+                &crate::ID,
+            ).map_err(|_| DiceError::InvalidBetAccounts)?;
+            require_keys_eq!(bet_key, bet.key(), DiceError::InvalidBetAccounts);
+            require_keys_eq!(player.key(), bet.player, DiceError::InvalidBetAccounts);
+            require!(!bet.commit_reveal(), DiceError::WrongRandomnessMode);
+
+            let roll = roll_from_hash(&hash(sig).to_bytes());
+
+            let payout = bet.payout()?;
+            self.bankroll.release(payout)?;
+
+            if bet.roll > roll {
+                let accounts = Transfer {
+                    from: self.vault.to_account_info(),
+                    to: player.clone()
+                };
+
+                let seeds = [b"vault", &self.house.key().to_bytes()[..], &[bumps.vault]];
+                let signer_seeds = &[&seeds[..]][..];
+
+                let ctx = CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    accounts,
+                    signer_seeds
+                );
+                transfer(ctx, payout)?;
+            }
+
+            bet.close(player.clone())?;
+        }
+        Ok(())
+    }
+}
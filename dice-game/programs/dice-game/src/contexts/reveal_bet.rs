@@ -0,0 +1,64 @@
+use anchor_lang::{prelude::*, system_program::{Transfer, transfer}};
+use solana_program::hash::{hash, hashv};
+
+use crate::{state::{Bet, Bankroll}, errors::DiceError, contexts::roll_from_hash};
+
+#[derive(Accounts)]
+pub struct RevealBet<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub house: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", house.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bankroll", house.key().as_ref()],
+        bump = bankroll.bump
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+    #[account(
+        mut,
+        has_one = player,
+        close = player,
+        seeds = [b"bet", vault.key().as_ref(), bet.seed.to_le_bytes().as_ref()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> RevealBet<'info> {
+    /// Settles a signed commit-reveal bet. The roll hashes the resolver's signature
+    /// together with the secret, which were each fixed before the other was known.
+    pub fn reveal_bet(&mut self, secret: [u8; 32], bumps: &RevealBetBumps) -> Result<()> {
+        require!(self.bet.signed(), DiceError::BetNotSigned);
+        require!(hash(&secret).to_bytes() == self.bet.commitment, DiceError::InvalidReveal);
+
+        let roll = roll_from_hash(&hashv(&[&self.bet.house_hash, &secret]).to_bytes());
+
+        let payout = self.bet.payout()?;
+        self.bankroll.release(payout)?;
+
+        if self.bet.roll > roll {
+            let accounts = Transfer {
+                from: self.vault.to_account_info(),
+                to: self.player.to_account_info()
+            };
+
+            let seeds = [b"vault", &self.house.key().to_bytes()[..], &[bumps.vault]];
+            let signer_seeds = &[&seeds[..]][..];
+
+            let ctx = CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                accounts,
+                signer_seeds
+            );
+            transfer(ctx, payout)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+
+use crate::{state::{Bet, HouseConfig}, errors::DiceError, contexts::verify_ed25519};
+
+#[derive(Accounts)]
+pub struct SignBet<'info> {
+    pub resolver: Signer<'info>,
+    pub house: SystemAccount<'info>,
+    #[account(
+        seeds = [b"vault", house.key().as_ref()],
+        bump
+    )]
+    pub vault: SystemAccount<'info>,
+    #[account(
+        seeds = [b"config", house.key().as_ref()],
+        bump = config.bump,
+        constraint = config.settings.resolver == resolver.key() @ DiceError::InvalidResolver
+    )]
+    pub config: Account<'info, HouseConfig>,
+    #[account(
+        mut,
+        seeds = [b"bet", vault.key().as_ref(), bet.seed.to_le_bytes().as_ref()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(
+        address = solana_program::sysvar::instructions::ID
+    )]
+    /// CHECK: This is safe
+    pub instruction_sysvar: AccountInfo<'info>,
+}
+
+impl<'info> SignBet<'info> {
+    /// The resolver's half of a commit-reveal bet. It signs without knowing the
+    /// player's secret, so it can't tell whether the bet will win, and after this
+    /// the bet can no longer be refunded.
+    pub fn sign_bet(&mut self, sig: &[u8]) -> Result<()> {
+        require!(self.bet.commit_reveal(), DiceError::WrongRandomnessMode);
+        require!(!self.bet.signed(), DiceError::BetAlreadySigned);
+        verify_ed25519(&self.instruction_sysvar, &self.resolver.key(), &self.bet.to_slice(), sig)?;
+
+        self.bet.house_hash = hash(sig).to_bytes();
+        self.bet.signed_slot = Clock::get()?.slot;
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{HouseConfig, HouseSettings};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub house: Signer<'info>,
+    #[account(
+        mut,
+        has_one = house,
+        seeds = [b"config", house.key().as_ref()],
+        bump = config.bump
+    )]
+    pub config: Account<'info, HouseConfig>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    /// Replaces the house rules. Open bets keep the edge they were placed with.
+    pub fn update_config(&mut self, settings: HouseSettings) -> Result<()> {
+        settings.validate()?;
+        self.config.settings = settings;
+        Ok(())
+    }
+
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        self.config.paused = paused;
+        Ok(())
+    }
+}
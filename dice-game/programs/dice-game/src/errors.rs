@@ -6,13 +6,13 @@ pub enum DiceError {
     BumpError,
     #[msg("Overflow")]
     Overflow,
-    #[msg("Minimum bet is 0.01 Sol")]
+    #[msg("Bet is below the house minimum")]
     MinimumBet,
     #[msg("Maximum bet exceeded")]
     MaximumBet,
-    #[msg("Minimum roll is 2")]
+    #[msg("Roll is below the house minimum")]
     MinimumRoll,
-    #[msg("Maximum roll is 96")]
+    #[msg("Roll is above the house maximum")]
     MaximumRoll,
     #[msg("Timeout not yet reached")]
     TimeoutNotReached,
@@ -29,5 +29,25 @@ pub enum DiceError {
     #[msg("Ed25119 Accounts Error")]
     Ed25519Accounts,
     #[msg("Ed25119 Data Length Error")]
-    Ed25519DataLength
+    Ed25519DataLength,
+    #[msg("Invalid parameter")]
+    InvalidParameter,
+    #[msg("Not enough unreserved bankroll")]
+    InsufficientBankroll,
+    #[msg("House is paused")]
+    HousePaused,
+    #[msg("Not the house resolver")]
+    InvalidResolver,
+    #[msg("Commitment doesn't match the house randomness mode")]
+    InvalidCommitment,
+    #[msg("Bet uses a different randomness mode")]
+    WrongRandomnessMode,
+    #[msg("Secret doesn't match the commitment")]
+    InvalidReveal,
+    #[msg("Bet hasn't been signed by the resolver")]
+    BetNotSigned,
+    #[msg("Bet has already been signed by the resolver")]
+    BetAlreadySigned,
+    #[msg("Expected a bet and its player for each signature")]
+    InvalidBetAccounts
 }
\ No newline at end of file
@@ -16,13 +16,14 @@ declare_id!("TMAdxoufbV32nWTkiCtr33hrHcN1aaiFUZeniN65DWu");
 pub mod dice_game {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, amount: u64) -> Result<()> {
-        ctx.accounts.init(amount)
+    pub fn initialize(ctx: Context<Initialize>, amount: u64, max_payout_bps: u16, settings: HouseSettings) -> Result<()> {
+        ctx.accounts.init(amount, max_payout_bps, settings, &ctx.bumps)
     }
 
-    pub fn place_bet(ctx: Context<PlaceBet>, seed: u128, roll: u8, amount: u64) -> Result<()> {
-        ctx.accounts.create_bet(seed, roll, amount, &ctx.bumps,)?;
-        ctx.accounts.deposit(amount)
+    pub fn place_bet(ctx: Context<PlaceBet>, seed: u128, roll: u8, amount: u64, commitment: [u8; 32]) -> Result<()> {
+        ctx.accounts.create_bet(seed, roll, amount, commitment, &ctx.bumps,)?;
+        ctx.accounts.deposit(amount)?;
+        ctx.accounts.reserve_payout()
     }
 
     pub fn resolve_bet(ctx: Context<ResolveBet>, sig: Vec<u8>) -> Result<()> {
@@ -30,7 +31,39 @@ pub mod dice_game {
         ctx.accounts.resolve_bet(&ctx.bumps, &sig)
     }
 
+    pub fn resolve_bets_batch<'info>(ctx: Context<'_, '_, 'info, 'info, ResolveBetsBatch<'info>>, sigs: Vec<Vec<u8>>) -> Result<()> {
+        ctx.accounts.resolve_bets_batch(&sigs, ctx.remaining_accounts, &ctx.bumps)
+    }
+
+    pub fn sign_bet(ctx: Context<SignBet>, sig: Vec<u8>) -> Result<()> {
+        ctx.accounts.sign_bet(&sig)
+    }
+
+    pub fn reveal_bet(ctx: Context<RevealBet>, secret: [u8; 32]) -> Result<()> {
+        ctx.accounts.reveal_bet(secret, &ctx.bumps)
+    }
+
+    pub fn forfeit_bet(ctx: Context<ForfeitBet>) -> Result<()> {
+        ctx.accounts.forfeit_bet()
+    }
+
     pub fn refund_bet(ctx: Context<RefundBet>) -> Result<()> {
         ctx.accounts.refund(&ctx.bumps)
     }
+
+    pub fn deposit_bankroll(ctx: Context<ManageBankroll>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_bankroll(amount)
+    }
+
+    pub fn withdraw_bankroll(ctx: Context<ManageBankroll>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_bankroll(amount, &ctx.bumps)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, settings: HouseSettings) -> Result<()> {
+        ctx.accounts.update_config(settings)
+    }
+
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.set_paused(paused)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::DiceError;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Bankroll {
+  pub house: Pubkey,
+  pub max_payout_bps: u16, // Largest payout one bet may win, as a share of the free bankroll
+  pub reserved: u64, // Potential payouts of every unresolved bet
+  pub bump: u8,
+}
+
+impl Bankroll {
+  /// Lamports in the vault not already promised to unresolved bets.
+  pub fn available(&self, vault_lamports: u64) -> u64 {
+    vault_lamports.saturating_sub(self.reserved)
+  }
+
+  pub fn max_payout(&self, vault_lamports: u64) -> Result<u64> {
+    Ok((self.available(vault_lamports) as u128)
+      .checked_mul(self.max_payout_bps as u128).ok_or(DiceError::Overflow)?
+      .checked_div(10000).ok_or(DiceError::Overflow)? as u64)
+  }
+
+  /// Sets aside `payout` for a new bet if it fits under the cap.
+  pub fn reserve(&mut self, payout: u64, vault_lamports: u64) -> Result<()> {
+    require!(payout <= self.max_payout(vault_lamports)?, DiceError::MaximumBet);
+    self.reserved = self.reserved.checked_add(payout).ok_or(DiceError::Overflow)?;
+    Ok(())
+  }
+
+  pub fn release(&mut self, payout: u64) -> Result<()> {
+    self.reserved = self.reserved.checked_sub(payout).ok_or(DiceError::Overflow)?;
+    Ok(())
+  }
+}
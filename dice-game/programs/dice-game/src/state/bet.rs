@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::DiceError;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Bet {
@@ -9,16 +11,46 @@ pub struct Bet {
   pub amount: u64,
   pub roll: u8,
   pub bump: u8,
+  pub house_edge: u16, // Edge at placing, so later config changes don't move the payout
+  pub commitment: [u8; 32], // Hash of the player's secret in commit-reveal mode, zeroed otherwise
+  pub house_hash: [u8; 32], // Hash of the resolver's signature once it signs a commit-reveal bet
+  pub signed_slot: u64, // Slot the resolver signed at, 0 until then
+  pub refund_timeout: u64, // Timeout at placing, so the house can't move it for open bets
 }
 
 impl Bet {
- 
+
+  /// What a winning bet pays back, stake included, after the house edge.
+  pub fn payout(&self) -> Result<u64> {
+    Ok((self.amount as u128)
+      .checked_mul(10000 - self.house_edge as u128).ok_or(DiceError::Overflow)?
+      .checked_div(self.roll as u128 - 1).ok_or(DiceError::Overflow)?
+      .checked_div(100).ok_or(DiceError::Overflow)? as u64)
+  }
+
+  pub fn commit_reveal(&self) -> bool {
+    self.commitment != [0; 32]
+  }
+
+  pub fn signed(&self) -> bool {
+    self.signed_slot != 0
+  }
+
+  /// Whether `refund_timeout` slots have passed since `from_slot`: the placing slot
+  /// for refunds of unsigned bets, the signing slot for forfeits of unrevealed ones.
+  pub fn timed_out(&self, from_slot: u64, current_slot: u64) -> Result<bool> {
+    Ok(current_slot >= from_slot.checked_add(self.refund_timeout).ok_or(DiceError::Overflow)?)
+  }
+
+  /// The terms the resolver signs; everything after `commitment` is filled in later.
   pub fn to_slice(&self) -> Vec<u8> {
     let mut s = self.player.to_bytes().to_vec();
     s.extend_from_slice(&self.seed.to_le_bytes());
     s.extend_from_slice(&self.slot.to_le_bytes());
     s.extend_from_slice(&self.amount.to_le_bytes());
     s.extend_from_slice(&[self.roll, self.bump]);
+    s.extend_from_slice(&self.house_edge.to_le_bytes());
+    s.extend_from_slice(&self.commitment);
     s        
   }
 }
\ No newline at end of file
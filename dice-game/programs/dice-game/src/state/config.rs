@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::{constants::{MAX_REFUND_TIMEOUT, MAX_ROLL, MIN_BET, MIN_ROLL}, errors::DiceError};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum RandomnessMode {
+  HouseSignature, // The roll comes from the resolver's signature alone
+  CommitReveal, // The roll mixes the resolver's signature with a secret the player committed to
+}
+
+/// House rules a bet is placed, resolved and refunded under.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, InitSpace)]
+pub struct HouseSettings {
+  pub resolver: Pubkey, // Key whose Ed25519 signature resolves bets, may differ from the house
+  pub house_edge: u16, // bps taken from every payout
+  pub min_bet: u64,
+  pub max_bet: u64,
+  pub min_roll: u8,
+  pub max_roll: u8,
+  pub refund_timeout: u64, // Slots after placing before an unresolved bet can be refunded, copied onto each bet
+  pub randomness: RandomnessMode,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct HouseConfig {
+  pub house: Pubkey,
+  pub settings: HouseSettings,
+  pub paused: bool, // Stops new bets; open ones can still be resolved or refunded
+  pub bump: u8,
+}
+
+impl HouseSettings {
+  pub fn validate(&self) -> Result<()> {
+    require!(self.house_edge < 10000, DiceError::InvalidParameter);
+    require!(self.min_bet >= MIN_BET && self.min_bet <= self.max_bet, DiceError::InvalidParameter);
+    require!(
+      self.min_roll >= MIN_ROLL && self.min_roll <= self.max_roll && self.max_roll <= MAX_ROLL,
+      DiceError::InvalidParameter
+    );
+    // A zero timeout would let a player refund or forfeit in the slot they bet, and a
+    // huge one would lock stakes in the vault for good
+    require!(
+      self.refund_timeout > 0 && self.refund_timeout <= MAX_REFUND_TIMEOUT,
+      DiceError::InvalidParameter
+    );
+    Ok(())
+  }
+
+  pub fn check_bet(&self, roll: u8, amount: u64, commitment: &[u8; 32]) -> Result<()> {
+    require!(roll >= self.min_roll, DiceError::MinimumRoll);
+    require!(roll <= self.max_roll, DiceError::MaximumRoll);
+    require!(amount >= self.min_bet, DiceError::MinimumBet);
+    require!(amount <= self.max_bet, DiceError::MaximumBet);
+    // Commit-reveal bets need a commitment and other bets must not carry one
+    require!(
+      (self.randomness == RandomnessMode::CommitReveal) == (commitment != &[0; 32]),
+      DiceError::InvalidCommitment
+    );
+    Ok(())
+  }
+}
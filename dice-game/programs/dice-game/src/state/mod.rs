@@ -1,3 +1,7 @@
 pub mod bet;
+pub mod bankroll;
+pub mod config;
 
-pub use bet::*;
\ No newline at end of file
+pub use bet::*;
+pub use bankroll::*;
+pub use config::*;
@@ -11,8 +11,9 @@ import {
   SYSVAR_INSTRUCTIONS_PUBKEY,
   sendAndConfirmTransaction,
 } from "@solana/web3.js";
-import { randomBytes } from "crypto";
+import { createHash, randomBytes } from "crypto";
 import { BN } from "bn.js";
+import { expect } from "chai";
 
 describe("dice-game", () => {
   // Configure the client to use the local cluster.
@@ -21,22 +22,46 @@ describe("dice-game", () => {
   const program = anchor.workspace.DiceGame as Program<DiceGame>;
 
   const MSG = Uint8Array.from(Buffer.from("1337", "hex"));
+  const REFUND_TIMEOUT = 10; // slots
+  const MAX_REFUND_TIMEOUT = 216_000;
   let house = new Keypair();
   let player = new Keypair();
+  let resolver = new Keypair();
   let seed = new BN(randomBytes(16));
   let vault = PublicKey.findProgramAddressSync(
     [Buffer.from("vault"), house.publicKey.toBuffer()],
     program.programId
   )[0];
+  let bankroll = PublicKey.findProgramAddressSync(
+    [Buffer.from("bankroll"), house.publicKey.toBuffer()],
+    program.programId
+  )[0];
+  let config = PublicKey.findProgramAddressSync(
+    [Buffer.from("config"), house.publicKey.toBuffer()],
+    program.programId
+  )[0];
   let bet = PublicKey.findProgramAddressSync(
     [Buffer.from("bet"), vault.toBuffer(), seed.toBuffer("le", 16)],
     program.programId
   )[0];
   let signature: Uint8Array;
+  const settings = {
+    resolver: resolver.publicKey,
+    houseEdge: 150,
+    minBet: new BN(LAMPORTS_PER_SOL / 100),
+    maxBet: new BN(LAMPORTS_PER_SOL).mul(new BN(100)),
+    minRoll: 2,
+    maxRoll: 96,
+    refundTimeout: new BN(REFUND_TIMEOUT),
+    randomness: { houseSignature: {} },
+  };
+  // Bet fields the resolver signs, everything up to and including the commitment
+  const BET_TERMS_LEN = 100;
+  const NO_COMMITMENT = Array(32).fill(0);
 
   it("Airdrop", async () => {
     await Promise.all(
-      [house, player].map(async (k) => {
+      [house, player, resolver].map(async (k) => {
         return await anchor
           .getProvider()
           .connection.requestAirdrop(
@@ -51,10 +76,16 @@ describe("dice-game", () => {
   it("Initialize", async () => {
     // Add your test here.
     let signature = await program.methods
-      .initialize(new BN(LAMPORTS_PER_SOL).mul(new BN(100)))
+      .initialize(
+        new BN(LAMPORTS_PER_SOL).mul(new BN(100)),
+        1000,
+        settings
+      )
       .accounts({
         house: house.publicKey,
         vault,
+        bankroll,
+        config,
         systemProgram: SystemProgram.programId,
       })
       .signers([house])
@@ -62,48 +93,665 @@ describe("dice-game", () => {
       .then(confirmTx);
   });
 
-  it("Place a bet", async () => {
-    // Add your test here.
-    let signature = await program.methods
-      .placeBet(seed, 50, new BN(LAMPORTS_PER_SOL / 100))
+  const placeBet = (
+    betSeed: BN,
+    roll: number,
+    amount: BN,
+    commitment: number[] = NO_COMMITMENT
+  ) =>
+    program.methods
+      .placeBet(betSeed, roll, amount, commitment)
       .accounts({
         player: player.publicKey,
         house: house.publicKey,
         vault,
-        bet,
+        bankroll,
+        config,
+        bet: PublicKey.findProgramAddressSync(
+          [Buffer.from("bet"), vault.toBuffer(), betSeed.toBuffer("le", 16)],
+          program.programId
+        )[0],
         systemProgram: SystemProgram.programId,
       })
       .signers([player])
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal(code);
+      return;
+    }
+    expect.fail(`expected ${code}`);
+  };
+
+  const sha256 = (...parts: Buffer[]) =>
+    createHash("sha256").update(Buffer.concat(parts)).digest();
+
+  const setRandomness = (randomness: object) =>
+    program.methods
+      .updateConfig({ ...settings, randomness })
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
       .rpc()
       .then(confirmTx);
-  });
 
-  it("Resolve a bet", async () => {
-    let account = await anchor
-      .getProvider()
-      .connection.getAccountInfo(bet, "confirmed");
-    let sig_ix = Ed25519Program.createInstructionWithPrivateKey({
-      privateKey: house.secretKey,
-      message: account.data.subarray(8),
+  // Has the resolver sign a commit-reveal bet and returns its signature
+  const signBet = async (betKey: PublicKey) => {
+    const connection = program.provider.connection;
+    const account = await connection.getAccountInfo(betKey, "confirmed");
+    const sig_ix = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: resolver.secretKey,
+      message: account.data.subarray(8, 8 + BET_TERMS_LEN),
     });
+    const sig = Buffer.from(sig_ix.data.buffer.slice(16 + 32, 16 + 32 + 64));
+    const sign_ix = await program.methods
+      .signBet(sig)
+      .accounts({
+        resolver: resolver.publicKey,
+        house: house.publicKey,
+        vault,
+        config,
+        bet: betKey,
+        instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .instruction();
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(sig_ix).add(sign_ix),
+      [resolver]
+    );
+    return sig;
+  };
+
+  const waitForRefundTimeout = async () => {
+    const connection = program.provider.connection;
+    const startSlot = await connection.getSlot();
+    while ((await connection.getSlot()) <= startSlot + REFUND_TIMEOUT) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+  };
+
+  it("Rejects rolls and bets outside the limits", async () => {
+    const betSeed = new BN(randomBytes(16));
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await expectError(placeBet(betSeed, 1, amount), "MinimumRoll");
+    await expectError(placeBet(betSeed, 97, amount), "MaximumRoll");
+    await expectError(
+      placeBet(betSeed, 50, new BN(LAMPORTS_PER_SOL / 100 - 1)),
+      "MinimumBet"
+    );
+  });
+
+  it("Caps payouts at a share of the free bankroll", async () => {
+    // 10% of ~100 SOL is ~10 SOL; a roll of 50 pays about twice the stake
+    await expectError(
+      placeBet(new BN(randomBytes(16)), 50, new BN(6 * LAMPORTS_PER_SOL)),
+      "MaximumBet"
+    );
+  });
+
+  it("House can only withdraw unreserved bankroll", async () => {
+    const betSeed = new BN(randomBytes(16));
+    await placeBet(betSeed, 50, new BN(LAMPORTS_PER_SOL)).then(confirmTx);
+
+    const { reserved } = await program.account.bankroll.fetch(bankroll);
+    const vaultBalance = await program.provider.connection.getBalance(vault);
+    const accounts = {
+      house: house.publicKey,
+      vault,
+      bankroll,
+      systemProgram: SystemProgram.programId,
+    };
+
+    await expectError(
+      program.methods
+        .withdrawBankroll(new BN(vaultBalance).sub(reserved))
+        .accounts(accounts)
+        .signers([house])
+        .rpc(),
+      "InsufficientBankroll"
+    );
+
+    await program.methods
+      .withdrawBankroll(new BN(LAMPORTS_PER_SOL))
+      .accounts(accounts)
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    await program.methods
+      .depositBankroll(new BN(LAMPORTS_PER_SOL))
+      .accounts(accounts)
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    expect(await program.provider.connection.getBalance(vault)).to.equal(
+      vaultBalance
+    );
+  });
+
+  it("Refunds a bet only after the timeout, and only once", async () => {
+    const betSeed = new BN(randomBytes(16));
+    const amount = new BN(LAMPORTS_PER_SOL / 10);
+    await placeBet(betSeed, 50, amount).then(confirmTx);
+
+    const refund = () =>
+      program.methods
+        .refundBet()
+        .accounts({
+          player: player.publicKey,
+          casino: house.publicKey,
+          vault,
+          bankroll,
+          bet: PublicKey.findProgramAddressSync(
+            [Buffer.from("bet"), vault.toBuffer(), betSeed.toBuffer("le", 16)],
+            program.programId
+          )[0],
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+
+    await expectError(refund(), "TimeoutNotReached");
+
+    // Raising the timeout afterwards doesn't hold back a bet already placed
+    await program.methods
+      .updateConfig({ ...settings, refundTimeout: new BN(MAX_REFUND_TIMEOUT) })
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+
+    const connection = program.provider.connection;
+    await waitForRefundTimeout();
+
+    const { reserved } = await program.account.bankroll.fetch(bankroll);
+    const vaultBalance = await connection.getBalance(vault);
+    await refund().then(confirmTx);
+    // Only the stake leaves the vault, and its payout is no longer reserved
+    expect(await connection.getBalance(vault)).to.equal(
+      vaultBalance - amount.toNumber()
+    );
+    const after = await program.account.bankroll.fetch(bankroll);
+    expect(after.reserved.lt(reserved)).to.be.true;
+
+    await expectError(refund(), "AccountNotInitialized");
+    await program.methods
+      .updateConfig(settings)
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+  });
+
+  it("House can change its limits and pause betting", async () => {
+    await program.methods
+      .updateConfig({ ...settings, maxRoll: 50 })
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    await expectError(
+      placeBet(new BN(randomBytes(16)), 60, new BN(LAMPORTS_PER_SOL / 10)),
+      "MaximumRoll"
+    );
+    // Settings can only tighten the program's hard limits, never widen them
+    for (const outOfBounds of [
+      { minRoll: 1 },
+      { maxRoll: 97 },
+      { minBet: new BN(LAMPORTS_PER_SOL / 100 - 1) },
+      { refundTimeout: new BN(MAX_REFUND_TIMEOUT + 1) },
+    ]) {
+      await expectError(
+        program.methods
+          .updateConfig({ ...settings, ...outOfBounds })
+          .accounts({ house: house.publicKey, config })
+          .signers([house])
+          .rpc(),
+        "InvalidParameter"
+      );
+    }
+    await expectError(
+      program.methods
+        .updateConfig({ ...settings, refundTimeout: new BN(0) })
+        .accounts({ house: house.publicKey, config })
+        .signers([house])
+        .rpc(),
+      "InvalidParameter"
+    );
+
+    await program.methods
+      .setPaused(true)
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    await expectError(
+      placeBet(new BN(randomBytes(16)), 50, new BN(LAMPORTS_PER_SOL / 10)),
+      "HousePaused"
+    );
+
+    await program.methods
+      .updateConfig(settings)
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    await program.methods
+      .setPaused(false)
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+  });
+
+  it("Commit-reveal bets need both the resolver and the player", async () => {
+    const connection = program.provider.connection;
+    await setRandomness({ commitReveal: {} });
+
+    // The player fixes their secret before the resolver signs
+    const secret = randomBytes(32);
+    const betSeed = new BN(randomBytes(16));
+    const betKey = PublicKey.findProgramAddressSync(
+      [Buffer.from("bet"), vault.toBuffer(), betSeed.toBuffer("le", 16)],
+      program.programId
+    )[0];
+    await expectError(
+      placeBet(betSeed, 50, new BN(LAMPORTS_PER_SOL / 10)),
+      "InvalidCommitment"
+    );
+    await placeBet(betSeed, 50, new BN(LAMPORTS_PER_SOL / 10), [
+      ...sha256(secret),
+    ]).then(confirmTx);
+
+    const revealAccounts = {
+      player: player.publicKey,
+      house: house.publicKey,
+      vault,
+      bankroll,
+      bet: betKey,
+      systemProgram: SystemProgram.programId,
+    };
+    await expectError(
+      program.methods
+        .revealBet([...secret])
+        .accounts(revealAccounts)
+        .signers([player])
+        .rpc(),
+      "BetNotSigned"
+    );
 
-    const resolve_ix = await program.methods
-      .resolveBet(Buffer.from(sig_ix.data.buffer.slice(16 + 32, 16 + 32 + 64)))
+    // The resolver signs without knowing the secret, so it can't tell a losing
+    // bet from a winning one and has nothing to grind or withhold on
+    const sig = await signBet(betKey);
+
+    // Now the player could compute the roll, so backing out is closed off
+    await expectError(
+      program.methods
+        .refundBet()
+        .accounts({ ...revealAccounts, casino: house.publicKey })
+        .signers([player])
+        .rpc(),
+      "BetAlreadySigned"
+    );
+    // And the committed secret is the only one accepted
+    await expectError(
+      program.methods
+        .revealBet([...randomBytes(32)])
+        .accounts(revealAccounts)
+        .signers([player])
+        .rpc(),
+      "InvalidReveal"
+    );
+
+    const entropy = sha256(sha256(sig), secret);
+    const roll = new BN(entropy.subarray(0, 16), "le")
+      .add(new BN(entropy.subarray(16, 32), "le"))
+      .maskn(128)
+      .modn(100) + 1;
+    const { amount, houseEdge } = await program.account.bet.fetch(betKey);
+    const payout = amount.muln(10000 - houseEdge).divn(50 - 1).divn(100);
+
+    const vaultBalance = await connection.getBalance(vault);
+    await program.methods
+      .revealBet([...secret])
+      .accounts(revealAccounts)
+      .signers([player])
+      .rpc()
+      .then(confirmTx);
+    expect(vaultBalance - (await connection.getBalance(vault))).to.equal(
+      50 > roll ? payout.toNumber() : 0
+    );
+
+    await setRandomness({ houseSignature: {} });
+  });
+
+  // A signed commit-reveal bet whose player never reveals
+  const forfeitSeed = new BN(randomBytes(16));
+  const forfeitedBet = PublicKey.findProgramAddressSync(
+    [Buffer.from("bet"), vault.toBuffer(), forfeitSeed.toBuffer("le", 16)],
+    program.programId
+  )[0];
+  const forfeit = () =>
+    program.methods
+      .forfeitBet()
       .accounts({
         player: player.publicKey,
         house: house.publicKey,
         vault,
-        bet,
+        bankroll,
+        bet: forfeitedBet,
+      })
+      .rpc();
+
+  it("Forfeit before the reveal deadline fails", async () => {
+    await setRandomness({ commitReveal: {} });
+    await placeBet(forfeitSeed, 50, new BN(LAMPORTS_PER_SOL / 10), [
+      ...sha256(randomBytes(32)),
+    ]).then(confirmTx);
+
+    // Nothing to forfeit until the resolver has signed
+    await expectError(forfeit(), "BetNotSigned");
+    await signBet(forfeitedBet);
+    // And then the player still has the refund timeout to reveal
+    await expectError(forfeit(), "TimeoutNotReached");
+
+    // Even if the house shortens the timeout once the bet is signed
+    await program.methods
+      .updateConfig({
+        ...settings,
+        refundTimeout: new BN(1),
+        randomness: { commitReveal: {} },
+      })
+      .accounts({ house: house.publicKey, config })
+      .signers([house])
+      .rpc()
+      .then(confirmTx);
+    const { signedSlot } = await program.account.bet.fetch(forfeitedBet);
+    const connection = program.provider.connection;
+    while ((await connection.getSlot()) <= signedSlot.toNumber() + 1) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    await expectError(forfeit(), "TimeoutNotReached");
+
+    await setRandomness({ houseSignature: {} });
+  });
+
+  it("Forfeit after the deadline pays the house and closes the bet", async () => {
+    const connection = program.provider.connection;
+    await waitForRefundTimeout();
+
+    const { amount, houseEdge } = await program.account.bet.fetch(
+      forfeitedBet
+    );
+    const payout = amount.muln(10000 - houseEdge).divn(50 - 1).divn(100);
+    const { reserved } = await program.account.bankroll.fetch(bankroll);
+    const vaultBalance = await connection.getBalance(vault);
+    const rent = await connection.getBalance(forfeitedBet);
+    const playerBalance = await connection.getBalance(player.publicKey);
+
+    // Anyone can crank it; the provider wallet isn't party to the bet
+    await forfeit().then(confirmTx);
+
+    // The house keeps the stake and its reserved payout is freed
+    expect(await connection.getBalance(vault)).to.equal(vaultBalance);
+    const after = await program.account.bankroll.fetch(bankroll);
+    expect(reserved.sub(after.reserved).eq(payout)).to.be.true;
+    // Only the bet's rent goes back to the player
+    expect(await connection.getBalance(player.publicKey)).to.equal(
+      playerBalance + rent
+    );
+    expect(await connection.getAccountInfo(forfeitedBet)).to.be.null;
+
+    await expectError(forfeit(), "AccountNotInitialized");
+  });
+
+  // One Ed25519 instruction holding a resolver signature over each message
+  const ed25519Batch = (messages: Buffer[]) => {
+    const entries = messages.map((message) => {
+      const { data } = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: resolver.secretKey,
+        message,
+      });
+      return {
+        signature: data.subarray(16 + 32, 16 + 32 + 64),
+        message,
+      };
+    });
+    const headerLen = 2 + 14 * entries.length;
+    const header = Buffer.alloc(headerLen);
+    header.writeUInt8(entries.length, 0);
+    const body = [];
+    let offset = headerLen;
+    entries.forEach(({ signature, message }, i) => {
+      const fields = [
+        offset + 32,
+        0xffff,
+        offset,
+        0xffff,
+        offset + 32 + 64,
+        message.length,
+        0xffff,
+      ];
+      fields.forEach((value, j) =>
+        header.writeUInt16LE(value, 2 + 14 * i + 2 * j)
+      );
+      body.push(resolver.publicKey.toBuffer(), signature, message);
+      offset += 32 + 64 + message.length;
+    });
+    return {
+      ix: new anchor.web3.TransactionInstruction({
+        programId: Ed25519Program.programId,
+        keys: [],
+        data: Buffer.concat([header, ...body]),
+      }),
+      signatures: entries.map(({ signature }) => Buffer.from(signature)),
+    };
+  };
+
+  const betMessage = async (betKey: PublicKey) =>
+    (
+      await program.provider.connection.getAccountInfo(betKey, "confirmed")
+    ).data.subarray(8, 8 + BET_TERMS_LEN);
+
+  // Settles each [bet, player] pair against the matching signature
+  const resolveBatchIx = (signatures: Buffer[], pairs: PublicKey[][]) =>
+    program.methods
+      .resolveBetsBatch(signatures)
+      .accounts({
+        resolver: resolver.publicKey,
+        house: house.publicKey,
+        vault,
+        bankroll,
+        config,
         instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
         systemProgram: SystemProgram.programId,
       })
-      .signers([house])
+      .remainingAccounts(
+        pairs.flat().map((pubkey) => ({
+          pubkey,
+          isSigner: false,
+          isWritable: true,
+        }))
+      )
       .instruction();
 
-    const tx = new Transaction().add(sig_ix).add(resolve_ix);
+  const placeBets = async (count: number) => {
+    const bets = [];
+    for (let i = 0; i < count; i++) {
+      const betSeed = new BN(randomBytes(16));
+      await placeBet(betSeed, 50, new BN(LAMPORTS_PER_SOL / 10)).then(
+        confirmTx
+      );
+      bets.push(
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("bet"), vault.toBuffer(), betSeed.toBuffer("le", 16)],
+          program.programId
+        )[0]
+      );
+    }
+    return bets;
+  };
+
+  it("Resolves several bets in one transaction", async () => {
+    const connection = program.provider.connection;
+    const bets = await placeBets(3);
+
+    const { ix: sig_ix, signatures } = ed25519Batch(
+      await Promise.all(bets.map(betMessage))
+    );
+    const batch_ix = await resolveBatchIx(
+      signatures,
+      bets.map((betKey) => [betKey, player.publicKey])
+    );
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(sig_ix).add(batch_ix),
+      [resolver]
+    );
+    for (const betKey of bets) {
+      expect(await connection.getAccountInfo(betKey)).to.be.null;
+    }
+  });
+
+  it("Rejects malformed batches", async () => {
+    const connection = program.provider.connection;
+    const [first, second] = await placeBets(2);
+    const [firstMessage, secondMessage] = await Promise.all(
+      [first, second].map(betMessage)
+    );
+    const expectRejected = async (
+      sig_ix: anchor.web3.TransactionInstruction,
+      batch_ix: anchor.web3.TransactionInstruction,
+      code: string
+    ) => {
+      const rejected = await sendAndConfirmTransaction(
+        connection,
+        new Transaction().add(sig_ix).add(batch_ix),
+        [resolver]
+      ).then(
+        () => null,
+        (error) => error
+      );
+      expect(rejected?.logs?.join("\n")).to.contain(code);
+    };
+
+    // The same bet twice, validly signed both times, can't be paid out twice
+    const twice = ed25519Batch([firstMessage, firstMessage]);
+    await expectRejected(
+      twice.ix,
+      await resolveBatchIx(twice.signatures, [
+        [first, player.publicKey],
+        [first, player.publicKey],
+      ]),
+      "InvalidBetAccounts"
+    );
+
+    // A payout can't be routed to someone other than the bet's player
+    const pair = ed25519Batch([firstMessage, secondMessage]);
+    await expectRejected(
+      pair.ix,
+      await resolveBatchIx(pair.signatures, [
+        [first, player.publicKey],
+        [second, house.publicKey],
+      ]),
+      "InvalidBetAccounts"
+    );
+
+    // Every signature in the Ed25519 instruction has to be used
+    await expectRejected(
+      pair.ix,
+      await resolveBatchIx(pair.signatures.slice(0, 1), [
+        [first, player.publicKey],
+      ]),
+      "Ed25519DataLength"
+    );
+
+    // None of the rejected batches settled anything
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction()
+        .add(pair.ix)
+        .add(
+          await resolveBatchIx(pair.signatures, [
+            [first, player.publicKey],
+            [second, player.publicKey],
+          ])
+        ),
+      [resolver]
+    );
+    for (const betKey of [first, second]) {
+      expect(await connection.getAccountInfo(betKey)).to.be.null;
+    }
+  });
+
+  it("Place a bet", async () => {
+    // Add your test here.
+    let signature = await program.methods
+      .placeBet(seed, 50, new BN(LAMPORTS_PER_SOL / 100), NO_COMMITMENT)
+      .accounts({
+        player: player.publicKey,
+        house: house.publicKey,
+        vault,
+        bankroll,
+        config,
+        bet,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc()
+      .then(confirmTx);
+  });
+
+  it("Resolve a bet", async () => {
+    let account = await anchor
+      .getProvider()
+      .connection.getAccountInfo(bet, "confirmed");
+    const resolveTx = async (signer: Keypair) => {
+      let sig_ix = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: signer.secretKey,
+        message: account.data.subarray(8, 8 + BET_TERMS_LEN),
+      });
+
+      const resolve_ix = await program.methods
+        .resolveBet(
+          Buffer.from(sig_ix.data.buffer.slice(16 + 32, 16 + 32 + 64))
+        )
+        .accounts({
+          resolver: signer.publicKey,
+          house: house.publicKey,
+          player: player.publicKey,
+          vault,
+          bankroll,
+          config,
+          bet,
+          instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .instruction();
+
+      return new Transaction().add(sig_ix).add(resolve_ix);
+    };
+
+    // Only the configured resolver key can settle bets
+    const rejected = await sendAndConfirmTransaction(
+      program.provider.connection,
+      await resolveTx(house),
+      [house]
+    ).then(
+      () => null,
+      (error) => error
+    );
+    expect(rejected?.logs?.join("\n")).to.contain("InvalidResolver");
 
     try {
-      await sendAndConfirmTransaction(program.provider.connection, tx, [house]);
+      await sendAndConfirmTransaction(
+        program.provider.connection,
+        await resolveTx(resolver),
+        [resolver]
+      );
     } catch (error) {
       console.error(error);
       throw error;